@@ -1,7 +1,8 @@
 use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Running, StreamHandler};
 use actix_web_actors::ws;
+use stock::is_currency;
 
-use crate::messages::{Connected, SendClientMessage, UpdateUserSubscriptions};
+use crate::messages::{Connected, SendClientMessage, SubscriptionKind, UpdateUserSubscriptions};
 
 use super::user_store::UserStore;
 
@@ -56,12 +57,23 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for SocketSession {
                 let m = text.trim();
                 if m.starts_with('/') {
                     let v: Vec<&str> = m.splitn(2, ' ').collect();
-                    if v[0] == "/subscribe" {
-                        if !v[1].is_empty() {
-                            let subscriptions = v[1].split(",").map(|v| v.trim().into()).collect();
+                    let kind = match v[0] {
+                        "/subscribe" => Some(SubscriptionKind::Ticks),
+                        "/quotes" => Some(SubscriptionKind::Quotes),
+                        _ => None,
+                    };
+                    if let Some(kind) = kind {
+                        let (stocks, currency) =
+                            split_currency(v.get(1).copied().unwrap_or_default());
+
+                        if !stocks.is_empty() && currency.as_deref().is_none_or(is_currency) {
+                            let subscriptions =
+                                stocks.split(',').map(|v| v.trim().into()).collect();
 
                             self.addr.do_send(UpdateUserSubscriptions {
                                 subscriptions,
+                                kind,
+                                currency,
                                 user_id: self.user_id,
                             });
                         }
@@ -76,3 +88,15 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for SocketSession {
         }
     }
 }
+
+/// splits `APPL, GOOG EUR` into the stocks and the currency to convert their prices to,
+/// a word after a comma is one more stock rather than a currency
+fn split_currency(args: &str) -> (&str, Option<String>) {
+    let args = args.trim();
+    match args.rsplit_once(char::is_whitespace) {
+        Some((stocks, currency)) if !stocks.trim_end().ends_with(',') => {
+            (stocks.trim_end(), Some(currency.to_uppercase()))
+        }
+        _ => (args, None),
+    }
+}
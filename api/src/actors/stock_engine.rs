@@ -1,21 +1,40 @@
-use crate::{messages::StockUpdated, state::StockDataSink};
+use crate::{
+    config::ReplaySpeed,
+    messages::{MarketSessionChanged, StockAdjusted, StockHalted, StockResumed, StockUpdated},
+    persistence::{append, JournalSink},
+    state::StockDataSink,
+};
 use actix::{
-    clock::{interval_at, Instant},
+    clock::{delay_until, interval_at, Instant},
     Actor, Addr, Context,
 };
-use futures::StreamExt;
-use std::time::Duration;
+use futures::{future::poll_fn, StreamExt};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    task::Poll,
+    time::Duration,
+};
+use stock::{parse_csv, ImportMode, ImportedPrice, MarketEvent, Symbol, SymbolRegistry};
 
 use super::user_store::UserStore;
 
 const TICK_INTERVAL: u64 = 1;
 
 /// Stock Engine
-/// engine that generates ticks and informs UserStore of Stock Updates
+/// engine that generates ticks and informs UserStore of Stock Updates, corporate actions,
+/// halts, resumes and market session changes, stocks only tick while the market is open,
+/// with a replay it streams the recorded prices instead of simulating, otherwise
+/// with a journal every tick is journaled before UserStore hears of it
 /// this engine is the only place from where we are updating the AppState's stock data
 pub(crate) struct StockEngine {
     pub stock_data_sink: StockDataSink,
     pub user_store: Addr<UserStore>,
+    /// fixed seed makes every run produce the same tick sequence
+    pub seed: Option<u64>,
+    pub replay: Option<Replay>,
+    pub journal: Option<JournalSink>,
 }
 
 impl Actor for StockEngine {
@@ -25,18 +44,144 @@ impl Actor for StockEngine {
     fn started(&mut self, _ctx: &mut Self::Context) {
         let stock_data = self.stock_data_sink.clone();
         let user_store = self.user_store.clone();
-        let mut thread_rng = rand::thread_rng();
+        let rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let journal = self.journal.clone();
+
+        match self.replay.take() {
+            Some(replay) => actix_web::rt::spawn(replay.run(stock_data, user_store, rng)),
+            None => actix_web::rt::spawn(simulate(stock_data, user_store, rng, journal)),
+        }
+    }
+}
+
+/// ticks every stock once per TICK_INTERVAL and passes the market events on to UserStore
+async fn simulate(
+    stock_data: StockDataSink,
+    user_store: Addr<UserStore>,
+    mut rng: StdRng,
+    journal: Option<JournalSink>,
+) {
+    let mut task = interval_at(Instant::now(), Duration::from_secs(TICK_INTERVAL));
 
-        actix_web::rt::spawn(async move {
-            let mut task = interval_at(Instant::now(), Duration::from_secs(TICK_INTERVAL));
+    while task.next().await.is_some() {
+        let (events, journaled) = {
+            let mut stock_data = stock_data.write().unwrap();
+            let events = stock_data.generate_next_tick(&mut rng);
+            let journaled = append(journal.as_ref(), &stock_data, &events);
+            (events, journaled)
+        };
 
-            while task.next().await.is_some() {
-                stock_data
-                    .write()
-                    .unwrap()
-                    .generate_next_tick(&mut thread_rng);
-                user_store.do_send(StockUpdated {});
+        for event in events {
+            match event {
+                MarketEvent::Halted { stock, halt } => {
+                    user_store.do_send(StockHalted { stock, halt })
+                }
+                MarketEvent::Resumed { stock } => user_store.do_send(StockResumed { stock }),
+                MarketEvent::CorporateAction { stock, adjustment } => {
+                    user_store.do_send(StockAdjusted { stock, adjustment })
+                }
+                MarketEvent::SessionChanged { state } => {
+                    user_store.do_send(MarketSessionChanged { state })
+                }
+                MarketEvent::Jump { .. } => (),
             }
-        });
+        }
+        if journaled {
+            user_store.do_send(StockUpdated {});
+        }
     }
 }
+
+/// Replay
+/// recorded prices streamed as if they were live, at the pace they were recorded at
+pub(crate) struct Replay {
+    /// oldest first
+    prices: Vec<ImportedPrice>,
+    speed: ReplaySpeed,
+}
+
+impl Replay {
+    /// reads a recording in any of the csv layouts of `stock::parse_csv`
+    pub fn load(path: &str, speed: ReplaySpeed) -> io::Result<Self> {
+        let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+        let file = BufReader::new(File::open(path)?);
+        let replay = Self::new(
+            parse_csv(file).map_err(|err| invalid(err.to_string()))?,
+            speed,
+        );
+
+        SymbolRegistry::with_symbols(replay.stocks()).map_err(|err| invalid(err.to_string()))?;
+        Ok(replay)
+    }
+
+    pub fn new(mut prices: Vec<ImportedPrice>, speed: ReplaySpeed) -> Self {
+        prices.sort_by_key(|price| price.timestamp);
+        Self { prices, speed }
+    }
+
+    /// symbols of the recording, in the order they first traded
+    pub fn stocks(&self) -> Vec<Symbol> {
+        let mut stocks: Vec<Symbol> = vec![];
+        for price in &self.prices {
+            if !stocks.contains(&price.stock) {
+                stocks.push(price.stock.clone());
+            }
+        }
+        stocks
+    }
+
+    /// lists only the recorded stocks and records their prices, every batch of prices
+    /// sharing a timestamp goes out to UserStore as one update, handled before the next
+    /// batch is recorded so none is skipped however fast the replay
+    async fn run(self, stock_data: StockDataSink, user_store: Addr<UserStore>, mut rng: StdRng) {
+        stock_data
+            .write()
+            .unwrap()
+            .list_stocks(&self.stocks(), ImportMode::Replace)
+            .expect("symbols are checked when the recording is loaded");
+
+        let first = match self.prices.first() {
+            Some(price) => price.timestamp,
+            None => return,
+        };
+        let started = Instant::now();
+
+        for batch in self.prices.chunk_by(|a, b| a.timestamp == b.timestamp) {
+            match self.speed {
+                ReplaySpeed::Times(times) => {
+                    let offset = Duration::from_millis(batch[0].timestamp - first);
+                    delay_until(started + offset.div_f64(times)).await
+                }
+                ReplaySpeed::Max => yield_now().await,
+            }
+
+            {
+                let mut stock_data = stock_data.write().unwrap();
+                for price in batch {
+                    // stocks delisted while replaying are skipped
+                    stock_data.replay_price(price, &mut rng).ok();
+                }
+            }
+            user_store.send(StockUpdated {}).await.ok();
+        }
+    }
+}
+
+/// lets the other actors on this thread run before carrying on
+async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 
 use actix::{Actor, Addr, Context, Handler};
+use stock::{Conversion, Currency, StockData, Symbol};
 
 use crate::{
-    messages::{Connected, SendClientMessage, StockUpdated, UpdateUserSubscriptions},
+    messages::{
+        Connected, MarketSessionChanged, SendClientMessage, StockAdjusted, StockDelisted,
+        StockHalted, StockResumed, StockUpdated, SubscriptionKind, UpdateUserSubscriptions,
+    },
     state::StockDataSink,
 };
 
@@ -26,28 +30,70 @@ impl Actor for UserStore {
 impl Handler<StockUpdated> for UserStore {
     type Result = ();
 
-    /// on stock updates - iterate over all users and send them their subscribed prices
-    /// also performs crediting the users
+    /// on stock updates - iterate over all users and send them their subscribed prices,
+    /// quotes go out as a separate message, also performs crediting the users,
+    /// halted stocks are neither sent nor charged for
     fn handle(&mut self, _msg: StockUpdated, _ctx: &mut Self::Context) -> Self::Result {
         let stock_data = self.stock_data_sink.read().unwrap();
+        let ticking = |subscription: &&Subscription| !stock_data.is_halted(&subscription.stock);
 
-        for (_, user) in &mut self.users {
-            let subs = user.subscriptions.len() as u32;
+        for user in self.users.values_mut() {
+            let subs = (user.subscriptions.iter().filter(ticking).count()
+                + user.quote_subscriptions.iter().filter(ticking).count())
+                as u32;
 
             if subs > 0 && user.credits > 0 && user.credits >= subs {
-                let response = user
+                let ticks = user
                     .subscriptions
                     .iter()
-                    .filter(|stock| stock_data.get_last_price(stock).is_some())
-                    .map(|stock| {
-                        format!("{}: {}", stock, stock_data.get_last_price(stock).unwrap())
+                    .filter(ticking)
+                    .filter_map(|subscription| {
+                        let (conversion, currency) = subscription.conversion(&stock_data)?;
+                        let tick = stock_data
+                            .get_last_tick(&subscription.stock)?
+                            .converted(conversion);
+                        Some(format!(
+                            "{}: {}{} seq={} ts={}",
+                            subscription.stock, tick.price, currency, tick.seq, tick.timestamp
+                        ))
                     })
                     .collect::<Vec<String>>()
                     .join(",");
 
-                if !response.is_empty() {
-                    user.addr.do_send(SendClientMessage { message: response });
-                    user.credits = user.credits - subs;
+                let quotes = user
+                    .quote_subscriptions
+                    .iter()
+                    .filter(ticking)
+                    .filter_map(|subscription| {
+                        let (conversion, currency) = subscription.conversion(&stock_data)?;
+                        let quote = stock_data
+                            .get_quote(&subscription.stock)?
+                            .converted(conversion);
+                        Some(format!(
+                            "{}: bid={}{} bid_size={} ask={}{} ask_size={} ts={}",
+                            subscription.stock,
+                            quote.bid,
+                            currency,
+                            quote.bid_size,
+                            quote.ask,
+                            currency,
+                            quote.ask_size,
+                            quote.timestamp
+                        ))
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",");
+
+                let mut sent = false;
+                for response in [ticks, quotes] {
+                    if !response.is_empty() {
+                        user.addr.do_send(SendClientMessage { message: response });
+                        sent = true;
+                    }
+                }
+
+                if sent {
+                    user.credits -= subs;
                 }
             }
         }
@@ -59,16 +105,118 @@ impl Handler<UpdateUserSubscriptions> for UserStore {
 
     /// handles users subscriptions that are coming via websocket
     fn handle(&mut self, msg: UpdateUserSubscriptions, _ctx: &mut Self::Context) -> Self::Result {
-        let user = self.users.get_mut(&msg.user_id);
-        if user.is_some() {
-            let user = user.unwrap();
+        if let Some(user) = self.users.get_mut(&msg.user_id) {
+            let subscriptions = match msg.kind {
+                SubscriptionKind::Ticks => &mut user.subscriptions,
+                SubscriptionKind::Quotes => &mut user.quote_subscriptions,
+            };
             for stock in msg.subscriptions {
-                user.subscriptions.push(stock);
+                subscriptions.push(Subscription {
+                    stock,
+                    currency: msg.currency.clone(),
+                });
             }
         }
     }
 }
 
+impl Handler<StockDelisted> for UserStore {
+    type Result = ();
+
+    /// drops the delisted stock from every subscription and tells the affected users about it
+    fn handle(&mut self, msg: StockDelisted, _ctx: &mut Self::Context) -> Self::Result {
+        for user in self.users.values_mut() {
+            let subs = user.subscriptions.len() + user.quote_subscriptions.len();
+            user.subscriptions
+                .retain(|subscription| subscription.stock != msg.stock);
+            user.quote_subscriptions
+                .retain(|subscription| subscription.stock != msg.stock);
+
+            if user.subscriptions.len() + user.quote_subscriptions.len() != subs {
+                user.addr.do_send(SendClientMessage {
+                    message: format!("{}: delisted", msg.stock),
+                });
+            }
+        }
+    }
+}
+
+impl Handler<StockHalted> for UserStore {
+    type Result = ();
+
+    /// tells the subscribers of a stock that it stopped trading and until when
+    fn handle(&mut self, msg: StockHalted, _ctx: &mut Self::Context) -> Self::Result {
+        let until = match msg.halt.until {
+            Some(until) => format!(" until={}", until),
+            None => String::new(),
+        };
+        let message = format!("{}: halted ({}){}", msg.stock, msg.halt.reason, until);
+
+        for user in self
+            .users
+            .values()
+            .filter(|user| user.is_subscribed(&msg.stock))
+        {
+            user.addr.do_send(SendClientMessage {
+                message: message.clone(),
+            });
+        }
+    }
+}
+
+impl Handler<StockResumed> for UserStore {
+    type Result = ();
+
+    /// tells the subscribers of a stock that it trades again
+    fn handle(&mut self, msg: StockResumed, _ctx: &mut Self::Context) -> Self::Result {
+        for user in self
+            .users
+            .values()
+            .filter(|user| user.is_subscribed(&msg.stock))
+        {
+            user.addr.do_send(SendClientMessage {
+                message: format!("{}: resumed", msg.stock),
+            });
+        }
+    }
+}
+
+impl Handler<StockAdjusted> for UserStore {
+    type Result = ();
+
+    /// tells the subscribers of a stock about a split or dividend and how earlier prices moved
+    fn handle(&mut self, msg: StockAdjusted, _ctx: &mut Self::Context) -> Self::Result {
+        let adjustment = msg.adjustment;
+        let message = format!(
+            "{}: {} ts={} adjustment={}",
+            msg.stock, adjustment.action, adjustment.ex_date, adjustment.price_factor
+        );
+
+        for user in self
+            .users
+            .values()
+            .filter(|user| user.is_subscribed(&msg.stock))
+        {
+            user.addr.do_send(SendClientMessage {
+                message: message.clone(),
+            });
+        }
+    }
+}
+
+impl Handler<MarketSessionChanged> for UserStore {
+    type Result = ();
+
+    /// tells every user the market opened, closed or moved into extended hours
+    fn handle(&mut self, msg: MarketSessionChanged, _ctx: &mut Self::Context) -> Self::Result {
+        for user in self.users.values() {
+            user.addr.do_send(SendClientMessage {
+                message: format!("market: {}", msg.state),
+            });
+        }
+    }
+}
+
 impl Handler<Connected> for UserStore {
     type Result = ();
 
@@ -83,7 +231,8 @@ pub(crate) struct User {
     credits: u32,
     addr: Addr<SocketSession>,
     id: usize,
-    subscriptions: Vec<String>,
+    subscriptions: Vec<Subscription>,
+    quote_subscriptions: Vec<Subscription>,
 }
 
 impl User {
@@ -93,6 +242,36 @@ impl User {
             addr,
             id,
             subscriptions: vec![],
+            quote_subscriptions: vec![],
+        }
+    }
+
+    /// whether the user receives ticks or quotes of the stock
+    fn is_subscribed(&self, stock: &str) -> bool {
+        self.subscriptions
+            .iter()
+            .chain(&self.quote_subscriptions)
+            .any(|subscription| subscription.stock == stock)
+    }
+}
+
+/// a stock a user streams, with the currency its prices are converted to if any
+pub(crate) struct Subscription {
+    stock: Symbol,
+    currency: Option<Currency>,
+}
+
+impl Subscription {
+    /// how to convert the prices and the currency to print after them,
+    /// nothing is printed for unconverted prices, None if there is no rate yet
+    fn conversion(&self, stock_data: &StockData) -> Option<(Conversion, String)> {
+        match &self.currency {
+            Some(currency) => {
+                let conversion =
+                    stock_data.get_conversion(stock_data.get_currency(&self.stock)?, currency)?;
+                Some((conversion, format!(" {}", currency)))
+            }
+            None => Some((Conversion::NONE, String::new())),
         }
     }
 }
@@ -0,0 +1,472 @@
+use std::{collections::HashMap, env, num::NonZeroU64, time::Duration};
+use stock::{
+    is_currency, parse_spread_model, parse_trend_detector, CircuitBreaker, Currency, FxPair,
+    GeometricBrownianMotion, ImportMode, MovingAverage, PriceModel, RandomJumps, RetentionPolicy,
+    Symbol, TradingCalendar, UniformModel, MAX_SCALE,
+};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+const DEFAULT_TREND_DETECTOR: &str = "quarters:1000";
+const DEFAULT_SPREAD_MODEL: &str = "relative:5:0.5:100";
+const DEFAULT_SNAPSHOT_INTERVAL: u64 = 60;
+const DEFAULT_JOURNAL_ARCHIVES: usize = 1;
+const DEFAULT_FX_PAIRS: &str = "EURUSD,GBPUSD,USDJPY";
+
+/// which price model the stocks are simulated with
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PriceModelKind {
+    Uniform,
+    /// presets with the drift and volatility of some stocks overridden
+    Gbm(HashMap<Symbol, (f64, f64)>),
+}
+
+impl PriceModelKind {
+    /// builds a fresh model of this kind for the given stock
+    pub fn build(&self, stock: &str) -> Box<dyn PriceModel> {
+        match self {
+            PriceModelKind::Uniform => Box::new(UniformModel::default()),
+            PriceModelKind::Gbm(params) => {
+                let mut model = GeometricBrownianMotion::preset(stock);
+                if let Some(&(drift, volatility)) = params.get(stock) {
+                    model.drift = drift;
+                    model.volatility = volatility;
+                }
+                Box::new(model)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for PriceModelKind {
+    type Err = String;
+
+    /// `uniform`, `gbm` or `gbm,TSLA:0.2:0.6` which simulates TSLA with a drift of 0.2
+    /// and a volatility of 0.6 instead of its preset
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid price model {:?}", s);
+        let mut parts = s.split(',');
+
+        match parts.next().map(str::trim) {
+            Some("uniform") if parts.next().is_none() => Ok(PriceModelKind::Uniform),
+            Some("gbm") => {
+                let mut params = HashMap::new();
+                for part in parts {
+                    let fields: Vec<&str> = part.split(':').map(str::trim).collect();
+                    let (drift, volatility) = match fields[..] {
+                        [_, drift, volatility] => (
+                            drift.parse::<f64>().map_err(|_| invalid())?,
+                            volatility.parse::<f64>().map_err(|_| invalid())?,
+                        ),
+                        _ => return Err(invalid()),
+                    };
+                    if !drift.is_finite() || !volatility.is_finite() || volatility < 0.0 {
+                        return Err(invalid());
+                    }
+                    params.insert(fields[0].to_uppercase(), (drift, volatility));
+                }
+                Ok(PriceModelKind::Gbm(params))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// how fast a recording is replayed, `2x` is twice as fast as it was recorded
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ReplaySpeed {
+    Times(f64),
+    /// as fast as the ticks can be recorded
+    Max,
+}
+
+impl std::str::FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        if s == "max" {
+            return Ok(ReplaySpeed::Max);
+        }
+
+        match s.trim_end_matches('x').parse::<f64>() {
+            Ok(times) if times.is_finite() && times > 0.0 => Ok(ReplaySpeed::Times(times)),
+            _ => Err(format!("invalid replay speed {:?}", s)),
+        }
+    }
+}
+
+/// setting of every stock with overrides for some, `2,TSLA:3` is 2 for every stock but TSLA
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PerSymbol<T> {
+    pub default: T,
+    pub symbols: HashMap<Symbol, T>,
+}
+
+impl<T: Clone> PerSymbol<T> {
+    /// setting of the given stock
+    pub fn of(&self, stock: &str) -> T {
+        self.symbols.get(stock).unwrap_or(&self.default).clone()
+    }
+}
+
+impl<T: std::str::FromStr> std::str::FromStr for PerSymbol<T> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid setting {:?}", s);
+        let value = |text: &str| text.trim().parse::<T>().map_err(|_| invalid());
+
+        let mut parts = s.split(',');
+        let default = value(parts.next().unwrap_or_default())?;
+        let mut symbols = HashMap::new();
+        for part in parts {
+            let (symbol, setting) = part.split_once(':').ok_or_else(invalid)?;
+            symbols.insert(symbol.trim().to_uppercase(), value(setting)?);
+        }
+
+        Ok(Self { default, symbols })
+    }
+}
+
+/// Server options
+/// read once on startup from environment variables
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub address: String,
+    pub price_model: PriceModelKind,
+    pub seed: Option<u64>,
+    pub retention: RetentionPolicy,
+    pub averages: Vec<MovingAverage>,
+    /// spec of the trend detector, see `stock::parse_trend_detector`
+    pub trend_detector: String,
+    /// spec of the spread model, see `stock::parse_spread_model`
+    pub spread_model: String,
+    /// spec of the correlation between stocks, see `stock::CorrelationMatrix::parse`
+    pub correlation: Option<String>,
+    pub random_jumps: Option<RandomJumps>,
+    pub circuit_breaker: Option<CircuitBreaker>,
+    pub calendar: TradingCalendar,
+    /// csv file of historical prices recorded before the first tick
+    pub import_csv: Option<String>,
+    pub import_mode: ImportMode,
+    /// recorded prices the engine streams instead of simulating
+    pub replay: Option<String>,
+    pub replay_speed: ReplaySpeed,
+    /// file the stock data is restored from on startup and snapshotted to
+    pub snapshot: Option<String>,
+    pub snapshot_interval: Duration,
+    /// append-only record of the published ticks, replayed on top of the snapshot on startup
+    pub journal: Option<String>,
+    /// rotated journals kept next to the journal
+    pub journal_archives: usize,
+    /// decimal places of the listed stocks, as snapshotted or the default ones if not set
+    pub precision: Option<PerSymbol<u32>>,
+    /// currencies the listed stocks are quoted in, as snapshotted or dollars if not set
+    pub currencies: Option<PerSymbol<Currency>>,
+    /// fx rates that tick along with the stocks
+    pub fx_pairs: Vec<FxPair>,
+}
+
+impl Config {
+    /// reads the options from the environment variables, unicode ones only
+    pub fn from_env() -> Result<Self, String> {
+        let vars = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+
+        Self::from_vars(&vars)
+    }
+
+    /// fails on the first option that is set but does not parse
+    pub fn from_vars(vars: &HashMap<String, String>) -> Result<Self, String> {
+        let var = |name: &str| vars.get(name).cloned();
+
+        let address = var("ADDRESS").unwrap_or_else(|| DEFAULT_ADDRESS.into());
+
+        let price_model = require_var(vars, "PRICE_MODEL")?.unwrap_or(PriceModelKind::Uniform);
+
+        let seed = require_var(vars, "SEED")?;
+
+        let mut retention = RetentionPolicy::default();
+        if let Some(capacity) = require_var(vars, "RETENTION_TICKS")? {
+            retention.capacity = capacity;
+        }
+        retention.max_age = require_var(vars, "RETENTION_SECS")?.map(Duration::from_secs);
+
+        let averages =
+            require_list(vars, "AVERAGES")?.unwrap_or_else(|| MovingAverage::DEFAULTS.to_vec());
+
+        let trend_detector = var("TREND_DETECTOR").unwrap_or_else(|| DEFAULT_TREND_DETECTOR.into());
+        parse_trend_detector(&trend_detector)?;
+
+        let spread_model = var("SPREAD_MODEL").unwrap_or_else(|| DEFAULT_SPREAD_MODEL.into());
+        parse_spread_model(&spread_model)?;
+
+        let correlation = var("CORRELATION");
+
+        // probability:size, e.g. 0.001:0.05
+        let random_jumps = parse_numbers(vars, "JUMPS", |numbers| match *numbers {
+            [probability, size] if (0.0..=1.0).contains(&probability) && is_amount(size) => {
+                Some(RandomJumps { probability, size })
+            }
+            _ => None,
+        })?;
+
+        // percent:window_secs:halt_secs, e.g. 7:300:900
+        let circuit_breaker = parse_numbers(vars, "CIRCUIT_BREAKER", |numbers| match *numbers {
+            [percent, window, halt_for]
+                if percent > 0.0
+                    && percent.is_finite()
+                    && is_amount(window)
+                    && is_amount(halt_for) =>
+            {
+                Some(CircuitBreaker {
+                    threshold: percent / 100.0,
+                    window: Duration::from_secs_f64(window),
+                    halt_for: Duration::from_secs_f64(halt_for),
+                })
+            }
+            _ => None,
+        })?;
+
+        let mut calendar = match var("MARKET_HOURS") {
+            Some(spec) => TradingCalendar::parse(&spec)?,
+            None => TradingCalendar::default(),
+        };
+        // comma separated dates, e.g. 2026-12-25,2027-01-01
+        if let Some(holidays) = var("HOLIDAYS") {
+            for date in holidays.split(',').filter(|date| !date.trim().is_empty()) {
+                calendar.add_holiday(date)?;
+            }
+        }
+
+        let import_csv = var("IMPORT_CSV");
+        let import_mode = require_var(vars, "IMPORT_MODE")?.unwrap_or(ImportMode::Seed);
+
+        let replay = var("REPLAY_FILE");
+        let replay_speed = require_var(vars, "REPLAY_SPEED")?.unwrap_or(ReplaySpeed::Times(1.0));
+
+        let snapshot = var("SNAPSHOT_FILE");
+        let snapshot_interval = Duration::from_secs(
+            require_var(vars, "SNAPSHOT_SECS")?.map_or(DEFAULT_SNAPSHOT_INTERVAL, NonZeroU64::get),
+        );
+        let journal = var("JOURNAL_FILE");
+        let journal_archives =
+            require_var(vars, "JOURNAL_ARCHIVES")?.unwrap_or(DEFAULT_JOURNAL_ARCHIVES);
+        // a replay lists the recorded stocks afresh and would overwrite the state persisted
+        if replay.is_some() && (snapshot.is_some() || journal.is_some()) {
+            return Err(
+                "REPLAY_FILE can not be combined with SNAPSHOT_FILE or JOURNAL_FILE".into(),
+            );
+        }
+        let precision = require_var::<PerSymbol<u32>>(vars, "PRECISION")?;
+        if precision.as_ref().is_some_and(|precision| {
+            std::iter::once(&precision.default)
+                .chain(precision.symbols.values())
+                .any(|places| *places > MAX_SCALE)
+        }) {
+            return Err(format!(
+                "PRECISION must be at most {} decimal places",
+                MAX_SCALE
+            ));
+        }
+        let currencies = require_var::<PerSymbol<Currency>>(vars, "CURRENCIES")?;
+        if let Some(currencies) = &currencies {
+            if !std::iter::once(&currencies.default)
+                .chain(currencies.symbols.values())
+                .all(|currency| is_currency(currency))
+            {
+                return Err(format!("invalid CURRENCIES {:?}", vars["CURRENCIES"]));
+            }
+        }
+        // an empty list ticks no fx rates
+        let fx_pairs = match require_list(vars, "FX_PAIRS")? {
+            Some(fx_pairs) => fx_pairs,
+            None => parse_list(DEFAULT_FX_PAIRS)?,
+        };
+
+        Ok(Self {
+            address,
+            price_model,
+            seed,
+            retention,
+            averages,
+            trend_detector,
+            spread_model,
+            correlation,
+            random_jumps,
+            circuit_breaker,
+            calendar,
+            import_csv,
+            import_mode,
+            replay,
+            replay_speed,
+            snapshot,
+            snapshot_interval,
+            journal,
+            journal_archives,
+            precision,
+            currencies,
+            fx_pairs,
+        })
+    }
+}
+
+/// reads and parses a variable, None if unset, an error if it does not parse
+fn require_var<T: std::str::FromStr>(
+    vars: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    match vars.get(name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid {} {:?}", name, value)),
+        None => Ok(None),
+    }
+}
+
+/// reads and parses a comma separated list, None if unset, an error if any entry does not parse
+fn require_list<T: std::str::FromStr>(
+    vars: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<Vec<T>>, String> {
+    match vars.get(name) {
+        Some(value) => parse_list(value)
+            .map(Some)
+            .map_err(|_| format!("invalid {} {:?}", name, value)),
+        None => Ok(None),
+    }
+}
+
+/// entries of a comma separated list, blank ones are skipped
+fn parse_list<T: std::str::FromStr>(list: &str) -> Result<Vec<T>, T::Err> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// reads a `:` separated list of numbers and builds a setting out of them, None if unset,
+/// an error if any of them does not parse or `build` refuses them
+fn parse_numbers<T>(
+    vars: &HashMap<String, String>,
+    name: &str,
+    build: impl FnOnce(&[f64]) -> Option<T>,
+) -> Result<Option<T>, String> {
+    let value = match vars.get(name) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    value
+        .split(':')
+        .map(|number| number.trim().parse().ok())
+        .collect::<Option<Vec<f64>>>()
+        .and_then(|numbers| build(&numbers))
+        .map(Some)
+        .ok_or_else(|| format!("invalid {} {:?}", name, value))
+}
+
+/// finite and not negative
+fn is_amount(number: f64) -> bool {
+    number.is_finite() && number >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stock::AverageKind;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_price_model_config() {
+        assert_eq!("uniform".parse(), Ok(PriceModelKind::Uniform));
+        let gbm: PriceModelKind = "gbm,tsla:0.2:0.6".parse().unwrap();
+        let tsla = GeometricBrownianMotion {
+            drift: 0.2,
+            volatility: 0.6,
+            ..GeometricBrownianMotion::preset("TSLA")
+        };
+        assert_eq!(format!("{:?}", gbm.build("TSLA")), format!("{:?}", tsla));
+        assert_eq!(
+            format!("{:?}", gbm.build("GOOG")),
+            format!("{:?}", GeometricBrownianMotion::preset("GOOG"))
+        );
+
+        for invalid in ["gmb", "gbm,TSLA:0.2", "gbm,TSLA:x:0.6", "gbm,TSLA:0.2:-1"] {
+            assert!(invalid.parse::<PriceModelKind>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_replay_speed() {
+        assert_eq!("2x".parse(), Ok(ReplaySpeed::Times(2.0)));
+        assert_eq!("0.5".parse(), Ok(ReplaySpeed::Times(0.5)));
+        assert_eq!("MAX".parse(), Ok(ReplaySpeed::Max));
+        assert!("0x".parse::<ReplaySpeed>().is_err());
+    }
+
+    #[test]
+    fn test_per_symbol() {
+        let precision: PerSymbol<u32> = "2,brk:0".parse().unwrap();
+        assert_eq!((precision.of("BRK"), precision.of("NFLX")), (0, 2));
+        assert!("2,BRK".parse::<PerSymbol<u32>>().is_err());
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let config = Config::from_vars(&HashMap::new()).unwrap();
+        assert_eq!(config.averages, MovingAverage::DEFAULTS.to_vec());
+        assert_eq!(config.fx_pairs.len(), 3);
+        assert_eq!(
+            config.snapshot_interval,
+            Duration::from_secs(DEFAULT_SNAPSHOT_INTERVAL)
+        );
+
+        let config = Config::from_vars(&vars(&[
+            ("RETENTION_TICKS", "100"),
+            ("RETENTION_SECS", "60"),
+            ("AVERAGES", "sma5, ema10"),
+            ("IMPORT_MODE", "replace"),
+            ("REPLAY_SPEED", "max"),
+            ("SNAPSHOT_SECS", "30"),
+            ("CURRENCIES", "USD,SAP:EUR"),
+            ("FX_PAIRS", ""),
+        ]))
+        .unwrap();
+        assert_eq!(config.retention.capacity, 100);
+        assert_eq!(config.retention.max_age, Some(Duration::from_secs(60)));
+        assert_eq!(config.averages[1].kind, AverageKind::Exponential);
+        assert_eq!(config.import_mode, ImportMode::Replace);
+        assert_eq!(config.replay_speed, ReplaySpeed::Max);
+        assert_eq!(config.snapshot_interval, Duration::from_secs(30));
+        assert_eq!(config.currencies.unwrap().of("SAP"), "EUR");
+        assert!(config.fx_pairs.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_options() {
+        for (name, value) in [
+            ("RETENTION_TICKS", "lots"),
+            ("RETENTION_SECS", "-1"),
+            ("AVERAGES", "sma20,avg5"),
+            ("IMPORT_MODE", "merge"),
+            ("REPLAY_SPEED", "0x"),
+            ("SNAPSHOT_SECS", "0"),
+            ("JOURNAL_ARCHIVES", "-1"),
+            ("CURRENCIES", "USD,SAP:euro"),
+            ("FX_PAIRS", "EURUSD,EUR"),
+        ] {
+            let error = Config::from_vars(&vars(&[(name, value)])).unwrap_err();
+            assert_eq!(error, format!("invalid {} {:?}", name, value));
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use actix_web::{error::ErrorInternalServerError, web::Bytes, Error};
+use arrow_array::{
+    ArrayRef, Decimal128Array, RecordBatch, StringArray, TimestampMillisecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use futures::{stream, Stream};
+use parquet::arrow::ArrowWriter;
+use serde::{Deserialize, Serialize};
+use stock::{Symbol, Tick, Timestamp, MAX_SCALE};
+
+use crate::state::StockDataSink;
+
+/// ticks copied out of the stock data per chunk, the lock is released in between
+const CHUNK_SIZE: usize = 1000;
+
+/// layout of an exported history
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportFormat {
+    /// `symbol,timestamp,price,volume` rows under a header, the layout imports and replays read
+    Csv,
+    /// one json object per tick
+    Jsonl,
+    /// `symbol,timestamp,price,volume` columns, one row group per chunk, prices as decimals
+    /// of MAX_SCALE places
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// tick of an exported stock, one line of jsonl
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ExportedTick {
+    pub stock: Symbol,
+    #[serde(flatten)]
+    pub tick: Tick,
+}
+
+/// Export
+/// ticks of some stocks within `from..=to`, stock by stock and oldest first
+#[derive(Debug, Clone)]
+pub(crate) struct Export {
+    pub stocks: Vec<Symbol>,
+    pub from: Timestamp,
+    pub to: Timestamp,
+    pub format: ExportFormat,
+    /// prices and volumes adjusted for splits and dividends, as recorded otherwise
+    pub adjusted: bool,
+}
+
+/// where an export carries on from
+struct Cursor {
+    export: Export,
+    stock: usize,
+    /// sequence number of the last tick sent of the current stock
+    after: u64,
+    header: bool,
+    /// writer the parquet row groups are drained from as they are written,
+    /// taken when the footer goes out after the last stock
+    parquet: Option<ArrowWriter<Vec<u8>>>,
+}
+
+impl Export {
+    /// streams the export in chunks of at most CHUNK_SIZE ticks, each chunk holds the read
+    /// lock only while copying its ticks, so ticks recorded meanwhile make it in as well
+    pub fn stream(self, stock_data: StockDataSink) -> impl Stream<Item = Result<Bytes, Error>> {
+        let parquet = match self.format {
+            ExportFormat::Parquet => Some(
+                ArrowWriter::try_new(vec![], parquet_schema(), None)
+                    .expect("the parquet schema is supported"),
+            ),
+            _ => None,
+        };
+        let cursor = Cursor {
+            header: self.format == ExportFormat::Csv,
+            export: self,
+            stock: 0,
+            after: 0,
+            parquet,
+        };
+
+        stream::unfold(cursor, move |mut cursor| {
+            let stock_data = stock_data.clone();
+            async move {
+                if cursor.header {
+                    cursor.header = false;
+                    return Some((Ok(Bytes::from("symbol,timestamp,price,volume\n")), cursor));
+                }
+
+                loop {
+                    let stock = match cursor.export.stocks.get(cursor.stock) {
+                        Some(stock) => stock.clone(),
+                        None => {
+                            let footer = cursor.parquet.take()?.into_inner();
+                            return Some((
+                                footer.map(Bytes::from).map_err(ErrorInternalServerError),
+                                cursor,
+                            ));
+                        }
+                    };
+                    let ticks = cursor.export.chunk(&stock_data, &stock, cursor.after);
+
+                    match ticks.last() {
+                        Some(last) => cursor.after = last.seq,
+                        None => {
+                            cursor.stock += 1;
+                            cursor.after = 0;
+                            continue;
+                        }
+                    }
+                    if ticks.len() < CHUNK_SIZE {
+                        cursor.stock += 1;
+                        cursor.after = 0;
+                    }
+
+                    let chunk = cursor.format_ticks(&stock, &ticks);
+                    return Some((chunk, cursor));
+                }
+            }
+        })
+    }
+
+    /// next ticks of a stock after a sequence number, empty once there are none left
+    fn chunk(&self, stock_data: &StockDataSink, stock: &str, after: u64) -> Vec<Tick> {
+        let stock_data = stock_data.read().unwrap();
+        let ticks = match stock_data.get_history(stock) {
+            Some(history) if self.adjusted => history.ticks(),
+            Some(history) => history.traded_ticks(),
+            None => return vec![],
+        };
+        let start = ticks.partition_point(|tick| tick.seq <= after);
+
+        ticks[start..]
+            .iter()
+            .filter(|tick| tick.timestamp >= self.from)
+            .take_while(|tick| tick.timestamp <= self.to)
+            .take(CHUNK_SIZE)
+            .copied()
+            .collect()
+    }
+}
+
+impl Cursor {
+    /// ticks of a stock in the format of the export, parquet ones as a row group
+    fn format_ticks(&mut self, stock: &str, ticks: &[Tick]) -> Result<Bytes, Error> {
+        let mut chunk = String::new();
+
+        for tick in ticks {
+            match self.export.format {
+                ExportFormat::Csv => chunk.push_str(&format!(
+                    "{},{},{},{}\n",
+                    stock, tick.timestamp, tick.price, tick.volume
+                )),
+                ExportFormat::Jsonl => {
+                    let line = ExportedTick {
+                        stock: stock.into(),
+                        tick: *tick,
+                    };
+                    chunk.push_str(&serde_json::to_string(&line).unwrap());
+                    chunk.push('\n');
+                }
+                ExportFormat::Parquet => {
+                    return match self.parquet.as_mut() {
+                        Some(writer) => write_row_group(writer, stock, ticks),
+                        None => Err(ErrorInternalServerError("parquet footer already written")),
+                    }
+                }
+            }
+        }
+        Ok(Bytes::from(chunk))
+    }
+}
+
+fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("price", DataType::Decimal128(38, MAX_SCALE as i8), false),
+        Field::new("volume", DataType::UInt64, false),
+    ]))
+}
+
+/// writes ticks of a stock as a row group and drains what the writer wrote so far
+fn write_row_group(
+    writer: &mut ArrowWriter<Vec<u8>>,
+    stock: &str,
+    ticks: &[Tick],
+) -> Result<Bytes, Error> {
+    let prices = ticks
+        .iter()
+        .map(|tick| tick.price.units() as i128 * 10_i128.pow(MAX_SCALE - tick.price.scale()));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec![stock; ticks.len()])),
+        Arc::new(
+            TimestampMillisecondArray::from_iter_values(
+                ticks.iter().map(|tick| tick.timestamp as i64),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(
+            Decimal128Array::from_iter_values(prices)
+                .with_precision_and_scale(38, MAX_SCALE as i8)
+                .map_err(ErrorInternalServerError)?,
+        ),
+        Arc::new(UInt64Array::from_iter_values(
+            ticks.iter().map(|tick| tick.volume),
+        )),
+    ];
+    let batch =
+        RecordBatch::try_new(parquet_schema(), columns).map_err(ErrorInternalServerError)?;
+
+    writer.write(&batch).map_err(ErrorInternalServerError)?;
+    writer.flush().map_err(ErrorInternalServerError)?;
+    Ok(Bytes::from(std::mem::take(writer.inner_mut())))
+}
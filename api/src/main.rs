@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf, str::FromStr, time::Duration};
 
 use actix::{Actor, Addr};
 use actix_web::{
@@ -6,20 +6,42 @@ use actix_web::{
     App, Error, HttpRequest, HttpServer,
 };
 mod actors;
+mod config;
+mod export;
 mod messages;
+mod persistence;
 mod state;
 use actix_web_actors::ws;
-use actors::{socket_session::SocketSession, stock_engine::StockEngine, user_store::UserStore};
+use actors::{
+    socket_session::SocketSession,
+    stock_engine::{Replay, StockEngine},
+    user_store::UserStore,
+};
+use config::Config;
+use export::{Export, ExportFormat};
+use messages::{StockDelisted, StockHalted, StockResumed, StockUpdated};
 use serde::{Deserialize, Serialize};
 use state::AppState;
-use stock::StockSummary;
+use stock::{
+    is_currency, Adjustment, CorporateAction, Currency, Indicator, IndicatorValue, Interval,
+    JournalEntry, MovingAverage, NewOrder, OrderError, Quote, RegistryError, ScheduledAction,
+    ScheduledJump, SessionState, StockSummary, Tick, Timestamp, BASE_CURRENCY, MAX_SCALE,
+};
+
+const DEFAULT_CANDLE_LIMIT: usize = 200;
+const DEFAULT_DEPTH_LEVELS: usize = 10;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
 
-    let address = "127.0.0.1:3000";
-    let app_state = state::AppState::new();
+    let config = Config::from_env()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+    let app_state = state::AppState::new(&config)?;
+    let replay = match &config.replay {
+        Some(path) => Some(Replay::load(path, config.replay_speed)?),
+        None => None,
+    };
 
     let user_store: Addr<UserStore> = UserStore {
         users: HashMap::new(),
@@ -30,43 +52,484 @@ async fn main() -> std::io::Result<()> {
     let stock_engine: Addr<StockEngine> = StockEngine {
         stock_data_sink: app_state.stock_data.clone(),
         user_store: user_store.clone(),
+        seed: config.seed,
+        replay,
+        journal: app_state.journal.clone(),
     }
     .start();
 
+    let stock_data = app_state.stock_data.clone();
+    let journal = app_state.journal.clone();
+    let snapshot = config.snapshot.clone().map(PathBuf::from);
+    if let Some(path) = &snapshot {
+        actix_web::rt::spawn(persistence::snapshot_every(
+            stock_data.clone(),
+            journal.clone(),
+            path.clone(),
+            config.snapshot_interval,
+        ));
+    }
+
+    let address = config.address.clone();
+
     // Create Http server with websocket support
     HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
+            .data(config.clone())
             .data(stock_engine.clone())
             .data(user_store.clone())
             .route("/summary", web::get().to(get_summary))
+            .route("/candles", web::get().to(get_candles))
+            .route("/history", web::get().to(get_history))
+            .route("/history/export", web::get().to(export_history))
+            .route("/indicators", web::get().to(get_indicator))
+            .route("/orders", web::post().to(submit_order))
+            .route("/orders/{id}", web::delete().to(cancel_order))
+            .route("/depth", web::get().to(get_depth))
+            .route("/session", web::get().to(get_session))
+            .route("/admin/symbols", web::get().to(list_symbols))
+            .route("/admin/symbols", web::post().to(add_symbol))
+            .route("/admin/symbols/{symbol}", web::delete().to(delist_symbol))
+            .route("/admin/symbols/{symbol}/halt", web::post().to(halt_symbol))
+            .route(
+                "/admin/symbols/{symbol}/resume",
+                web::post().to(resume_symbol),
+            )
+            .route("/admin/symbols/{symbol}/jump", web::post().to(jump_symbol))
+            .route(
+                "/admin/symbols/{symbol}/actions",
+                web::post().to(schedule_action),
+            )
             .service(web::resource("/ws/").to(handle_subscribe))
     })
     .bind(address)?
     .run()
-    .await
+    .await?;
+
+    // the server stops on ctrl-c and SIGTERM, a last snapshot keeps the ticks since the previous one
+    match &snapshot {
+        Some(path) => persistence::save_snapshot(&stock_data, journal.as_ref(), path),
+        None => Ok(()),
+    }
 }
 
 async fn get_summary(state: Data<AppState>, query: web::Query<StockQuery>) -> HttpResponse {
+    let averages = match &query.averages {
+        Some(averages) => match parse_list::<MovingAverage>(averages) {
+            Ok(averages) => Some(averages),
+            Err(err) => return HttpResponse::BadRequest().body(err),
+        },
+        None => None,
+    };
+    let ccy = query.ccy.as_ref().map(|ccy| ccy.to_uppercase());
+    if let Some(ccy) = ccy.as_deref().filter(|ccy| !is_currency(ccy)) {
+        return HttpResponse::BadRequest().body(format!("invalid currency {:?}", ccy));
+    }
+
     let stock_data = state.stock_data.read().unwrap();
     let mut result = vec![];
 
     let summaries = stock_data.get_summaries();
 
-    for stock in query.stocks.split(",") {
-        if let Some(summary) = summaries.get(stock) {
-            if summary.is_some() {
-                result.push(SummaryResponse {
-                    stock: stock.into(),
-                    summary: summary.clone().unwrap(),
-                });
+    for stock in query.stocks.split(',') {
+        if let Some(Some(summary)) = summaries.get(stock) {
+            let own = stock_data.get_currency(stock).unwrap_or(BASE_CURRENCY);
+            let currency = ccy.clone().unwrap_or_else(|| own.into());
+            let conversion = match stock_data.get_conversion(own, &currency) {
+                Some(conversion) => conversion,
+                None => {
+                    return HttpResponse::NotFound()
+                        .body(format!("no fx rate from {} to {}", own, currency))
+                }
+            };
+
+            let mut summary = summary.clone();
+            if let Some(averages) = &averages {
+                summary.averages = stock_data
+                    .get_moving_averages(stock, averages)
+                    .unwrap_or_default();
             }
+
+            result.push(SummaryResponse {
+                stock: stock.into(),
+                currency,
+                summary: summary.converted(conversion),
+                last_tick: stock_data
+                    .get_last_tick(stock)
+                    .map(|tick| tick.converted(conversion)),
+                quote: stock_data
+                    .get_quote(stock)
+                    .map(|quote| quote.converted(conversion)),
+            });
         }
     }
 
     HttpResponse::Ok().json(result)
 }
 
+/// parses a comma separated list, failing on the first invalid entry
+fn parse_list<T: FromStr<Err = String>>(list: &str) -> Result<Vec<T>, String> {
+    list.split(',').map(str::parse).collect()
+}
+
+/// latest candles of a stock, oldest first, adjusted for splits and dividends unless told otherwise
+async fn get_candles(state: Data<AppState>, query: web::Query<CandleQuery>) -> HttpResponse {
+    let stock_data = state.stock_data.read().unwrap();
+    let limit = query.limit.unwrap_or(DEFAULT_CANDLE_LIMIT);
+
+    let candles = if query.adjusted.unwrap_or(true) {
+        stock_data.get_candles(&query.stock, query.interval, limit)
+    } else {
+        stock_data.get_raw_candles(&query.stock, query.interval, limit)
+    };
+
+    match candles {
+        Some(candles) => HttpResponse::Ok().json(candles),
+        None => HttpResponse::NotFound().body(format!("{} is not listed", query.stock)),
+    }
+}
+
+/// retained ticks of a stock along with the corporate actions they were adjusted for
+async fn get_history(state: Data<AppState>, query: web::Query<HistoryQuery>) -> HttpResponse {
+    let stock_data = state.stock_data.read().unwrap();
+    let adjusted = query.adjusted.unwrap_or(true);
+
+    let ticks = if adjusted {
+        stock_data
+            .get_history(&query.stock)
+            .map(|history| history.ticks().to_vec())
+    } else {
+        stock_data.get_raw_ticks(&query.stock)
+    };
+
+    match (ticks, stock_data.get_adjustments(&query.stock)) {
+        (Some(ticks), Some(adjustments)) => HttpResponse::Ok().json(HistoryResponse {
+            stock: query.stock.clone(),
+            adjusted,
+            adjustments: adjustments.to_vec(),
+            ticks,
+        }),
+        _ => HttpResponse::NotFound().body(format!("{} is not listed", query.stock)),
+    }
+}
+
+/// streams the retained ticks of some stocks within `from..=to` as csv, json lines or parquet,
+/// chunk by chunk rather than building the whole response up front
+async fn export_history(state: Data<AppState>, query: web::Query<ExportQuery>) -> HttpResponse {
+    let format = query.format.unwrap_or(ExportFormat::Csv);
+    let (from, to) = (query.from.unwrap_or(0), query.to.unwrap_or(Timestamp::MAX));
+    if from > to {
+        return HttpResponse::BadRequest().body("from must not be after to");
+    }
+
+    let stocks: Vec<String> = query.stocks.split(',').map(String::from).collect();
+    {
+        let stock_data = state.stock_data.read().unwrap();
+        if let Some(stock) = stocks
+            .iter()
+            .find(|stock| stock_data.get_history(stock).is_none())
+        {
+            return HttpResponse::NotFound().body(format!("{} is not listed", stock));
+        }
+    }
+
+    let export = Export {
+        stocks,
+        from,
+        to,
+        format,
+        adjusted: query.adjusted.unwrap_or(true),
+    };
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .streaming(Box::pin(export.stream(state.stock_data.clone())))
+}
+
+/// technical indicator of a stock, `value` is null while there is not enough data
+async fn get_indicator(state: Data<AppState>, query: web::Query<IndicatorQuery>) -> HttpResponse {
+    let indicator = match query.name.parse::<Indicator>() {
+        Ok(indicator) => indicator,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    let period = query.period.unwrap_or_else(|| indicator.default_period());
+    if period == 0 {
+        return HttpResponse::BadRequest().body("period must be positive");
+    }
+
+    let stock_data = state.stock_data.read().unwrap();
+    let retained = stock_data.get_retention().capacity;
+    if period > retained {
+        return HttpResponse::BadRequest().body(format!(
+            "period must be at most {}, the number of retained prices",
+            retained
+        ));
+    }
+
+    match stock_data.get_indicator(&query.stock, indicator, period) {
+        Some(value) => HttpResponse::Ok().json(IndicatorResponse {
+            stock: query.stock.clone(),
+            name: indicator,
+            period,
+            value,
+        }),
+        None => HttpResponse::NotFound().body(format!("{} is not listed", query.stock)),
+    }
+}
+
+/// matches an order against the book of its stock, trades show up as ticks right away
+/// and are pushed to subscribers once journaled
+async fn submit_order(
+    state: Data<AppState>,
+    user_store: Data<Addr<UserStore>>,
+    body: web::Json<OrderRequest>,
+) -> HttpResponse {
+    let mut stock_data = state.stock_data.write().unwrap();
+
+    match stock_data.submit_order(&body.stock, body.order) {
+        Ok(execution) => {
+            if !execution.trades.is_empty()
+                && persistence::append(state.journal.as_ref(), &stock_data, &[])
+            {
+                user_store.do_send(StockUpdated {});
+            }
+            HttpResponse::Ok().json(execution)
+        }
+        Err(err) => order_error_response(err),
+    }
+}
+
+/// takes a resting order out of its book
+async fn cancel_order(state: Data<AppState>, path: web::Path<u64>) -> HttpResponse {
+    let mut stock_data = state.stock_data.write().unwrap();
+
+    match stock_data.cancel_order(path.into_inner()) {
+        Ok(order) => HttpResponse::Ok().json(order),
+        Err(err) => order_error_response(err),
+    }
+}
+
+/// L2 depth of a stock's book, best prices first
+async fn get_depth(state: Data<AppState>, query: web::Query<DepthQuery>) -> HttpResponse {
+    let stock_data = state.stock_data.read().unwrap();
+    let levels = query.levels.unwrap_or(DEFAULT_DEPTH_LEVELS);
+
+    match stock_data.get_depth(&query.stock, levels) {
+        Some(depth) => HttpResponse::Ok().json(depth),
+        None => HttpResponse::NotFound().body(format!("{} is not listed", query.stock)),
+    }
+}
+
+fn order_error_response(err: OrderError) -> HttpResponse {
+    match err {
+        OrderError::InvalidQuantity | OrderError::InvalidPrice => {
+            HttpResponse::BadRequest().body(err.to_string())
+        }
+        OrderError::NotListed(_) | OrderError::UnknownOrder(_) => {
+            HttpResponse::NotFound().body(err.to_string())
+        }
+        OrderError::Halted(_) | OrderError::MarketClosed => {
+            HttpResponse::Conflict().body(err.to_string())
+        }
+    }
+}
+
+/// state of the market as of the latest tick
+async fn get_session(state: Data<AppState>) -> HttpResponse {
+    let stock_data = state.stock_data.read().unwrap();
+
+    HttpResponse::Ok().json(SessionResponse {
+        state: stock_data.get_session(),
+    })
+}
+
+/// lists every symbol that is currently ticking
+async fn list_symbols(state: Data<AppState>) -> HttpResponse {
+    let stock_data = state.stock_data.read().unwrap();
+
+    HttpResponse::Ok().json(stock_data.get_stocks())
+}
+
+/// lists a new symbol, priced by the model the server was configured with,
+/// to the given decimal places or the configured ones
+async fn add_symbol(
+    state: Data<AppState>,
+    config: Data<Config>,
+    body: web::Json<SymbolRequest>,
+) -> HttpResponse {
+    if body.precision.is_some_and(|places| places > MAX_SCALE) {
+        return HttpResponse::BadRequest().body(format!(
+            "precision must be at most {} decimal places",
+            MAX_SCALE
+        ));
+    }
+    let mut stock_data = state.stock_data.write().unwrap();
+    let model = config.price_model.build(&body.symbol.trim().to_uppercase());
+
+    match stock_data.add_stock(&body.symbol, model) {
+        Ok(symbol) => {
+            if let Some(places) = body.precision {
+                stock_data.set_precision(&symbol, places);
+            }
+            let precision = stock_data.get_precision(&symbol);
+            state.record(
+                &stock_data,
+                JournalEntry::Listed {
+                    stock: symbol.clone(),
+                    precision,
+                },
+            );
+            HttpResponse::Created().json(SymbolRequest { symbol, precision })
+        }
+        Err(err) => registry_error_response(err),
+    }
+}
+
+/// delists a symbol and lets its subscribers know
+async fn delist_symbol(
+    state: Data<AppState>,
+    user_store: Data<Addr<UserStore>>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let mut stock_data = state.stock_data.write().unwrap();
+
+    match stock_data.delist_stock(&path) {
+        Ok(symbol) => {
+            state.record(
+                &stock_data,
+                JournalEntry::Delisted {
+                    stock: symbol.clone(),
+                },
+            );
+            user_store.do_send(StockDelisted {
+                stock: symbol.clone(),
+            });
+            HttpResponse::Ok().json(SymbolRequest {
+                symbol,
+                precision: None,
+            })
+        }
+        Err(err) => registry_error_response(err),
+    }
+}
+
+/// halts a symbol for `seconds`, or until resumed, and lets its subscribers know
+async fn halt_symbol(
+    state: Data<AppState>,
+    user_store: Data<Addr<UserStore>>,
+    path: web::Path<String>,
+    query: web::Query<HaltQuery>,
+) -> HttpResponse {
+    let mut stock_data = state.stock_data.write().unwrap();
+    let symbol = match stock_data.get_symbol(&path) {
+        Ok(symbol) => symbol,
+        Err(err) => return registry_error_response(err),
+    };
+
+    match stock_data.halt_stock(&symbol, query.seconds.map(Duration::from_secs)) {
+        Ok(halt) => {
+            user_store.do_send(StockHalted {
+                stock: symbol,
+                halt,
+            });
+            HttpResponse::Ok().json(halt)
+        }
+        Err(err) => registry_error_response(err),
+    }
+}
+
+/// lifts the halt of a symbol and lets its subscribers know
+async fn resume_symbol(
+    state: Data<AppState>,
+    user_store: Data<Addr<UserStore>>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let mut stock_data = state.stock_data.write().unwrap();
+    let symbol = match stock_data.get_symbol(&path) {
+        Ok(symbol) => symbol,
+        Err(err) => return registry_error_response(err),
+    };
+
+    match stock_data.resume_stock(&symbol) {
+        Ok(Some(halt)) => {
+            user_store.do_send(StockResumed { stock: symbol });
+            HttpResponse::Ok().json(halt)
+        }
+        Ok(None) => HttpResponse::Conflict().body(format!("{} is not halted", symbol)),
+        Err(err) => registry_error_response(err),
+    }
+}
+
+/// jumps the price of a symbol on its first tick at or after `at`, on the next tick without it
+async fn jump_symbol(
+    state: Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<JumpRequest>,
+) -> HttpResponse {
+    if !body.size.is_finite() || body.size <= -1.0 {
+        return HttpResponse::BadRequest().body("size must be a finite move above -1");
+    }
+
+    let mut stock_data = state.stock_data.write().unwrap();
+    let jump = ScheduledJump {
+        stock: path.clone(),
+        at: body.at.unwrap_or(0),
+        size: body.size,
+    };
+
+    match stock_data.schedule_jump(jump) {
+        Ok(jump) => {
+            state.record(
+                &stock_data,
+                JournalEntry::JumpScheduled { jump: jump.clone() },
+            );
+            HttpResponse::Ok().json(jump)
+        }
+        Err(err) => registry_error_response(err),
+    }
+}
+
+/// schedules a split or dividend of a symbol for its first tick at or after `at`,
+/// the next tick without it
+async fn schedule_action(
+    state: Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<ActionRequest>,
+) -> HttpResponse {
+    if !body.action.is_valid() {
+        return HttpResponse::BadRequest().body("ratio and amount must be positive and finite");
+    }
+
+    let mut stock_data = state.stock_data.write().unwrap();
+    let action = ScheduledAction {
+        stock: path.clone(),
+        at: body.at.unwrap_or(0),
+        action: body.action,
+    };
+
+    match stock_data.schedule_action(action) {
+        Ok(action) => {
+            state.record(
+                &stock_data,
+                JournalEntry::ActionScheduled {
+                    action: action.clone(),
+                },
+            );
+            HttpResponse::Ok().json(action)
+        }
+        Err(err) => registry_error_response(err),
+    }
+}
+
+fn registry_error_response(err: RegistryError) -> HttpResponse {
+    match err {
+        RegistryError::InvalidSymbol(_) => HttpResponse::BadRequest().body(err.to_string()),
+        RegistryError::AlreadyListed(_) => HttpResponse::Conflict().body(err.to_string()),
+        RegistryError::NotListed(_) => HttpResponse::NotFound().body(err.to_string()),
+    }
+}
+
 /// Entry point for our websocket route
 async fn handle_subscribe(
     req: HttpRequest,
@@ -84,12 +547,110 @@ async fn handle_subscribe(
 #[derive(Deserialize, Serialize, Debug)]
 pub struct StockQuery {
     stocks: String,
+    /// comma separated averages to report instead of the configured ones, e.g. "sma5,ema10"
+    averages: Option<String>,
+    /// currency to convert the prices to instead of the one each stock is quoted in
+    ccy: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CandleQuery {
+    stock: String,
+    interval: Interval,
+    limit: Option<usize>,
+    adjusted: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct HistoryQuery {
+    stock: String,
+    adjusted: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ExportQuery {
+    stocks: String,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+    format: Option<ExportFormat>,
+    adjusted: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HistoryResponse {
+    stock: String,
+    adjusted: bool,
+    adjustments: Vec<Adjustment>,
+    ticks: Vec<Tick>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct IndicatorQuery {
+    stock: String,
+    name: String,
+    period: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IndicatorResponse {
+    stock: String,
+    name: Indicator,
+    period: usize,
+    value: Option<IndicatorValue>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DepthQuery {
+    stock: String,
+    levels: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct OrderRequest {
+    stock: String,
+    #[serde(flatten)]
+    order: NewOrder,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct HaltQuery {
+    seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct JumpRequest {
+    /// relative move, -0.1 drops the price by 10%
+    size: f64,
+    at: Option<Timestamp>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct ActionRequest {
+    #[serde(flatten)]
+    action: CorporateAction,
+    at: Option<Timestamp>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct SymbolRequest {
+    symbol: String,
+    /// decimal places the prices of the symbol are rounded to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    precision: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SessionResponse {
+    state: SessionState,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct SummaryResponse {
     stock: String,
+    currency: Currency,
     summary: StockSummary,
+    last_tick: Option<Tick>,
+    quote: Option<Quote>,
 }
 
 #[cfg(test)]
@@ -97,17 +658,26 @@ mod tests {
     use super::*;
     use actix_web::dev::{Service, ServiceResponse};
     use actix_web::{http, test, web, App};
-    use std::sync::{Arc, RwLock};
-    use stock::{StockData, StockTrend};
+    use arrow_array::{Array, Decimal128Array, RecordBatch, StringArray};
+    use export::ExportedTick;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use persistence::Journal;
+    use std::sync::{Arc, Mutex, RwLock};
+    use stock::{
+        Candle, Depth, Execution, Halt, MarketEvent, OrderStatus, StockData, StockTrend,
+        TradingCalendar,
+    };
 
     #[actix_rt::test]
     async fn test_get_summary() {
         let mut stock_data = StockData::initialize();
+        stock_data.add_fx_pair(&"EURUSD".parse().unwrap()).unwrap();
         let mut thread_rng = rand::thread_rng();
         stock_data.generate_next_tick(&mut thread_rng);
 
         let app_state = Data::new(AppState {
             stock_data: Arc::new(RwLock::new(stock_data)),
+            journal: None,
         });
 
         let app = App::new()
@@ -136,7 +706,730 @@ mod tests {
         assert_eq!(apple_summary.stock, "APPL");
         assert!(apple_summary.summary.highest_price.is_some());
         assert!(apple_summary.summary.lowest_price.is_some());
-        assert!(apple_summary.summary.moving_average > 0.0);
-        assert_eq!(apple_summary.summary.trend, StockTrend::NotEnoughData);
+        assert!(apple_summary.summary.moving_average.is_positive());
+        assert_eq!(
+            apple_summary.summary.trend.direction,
+            StockTrend::NotEnoughData
+        );
+        assert_eq!(apple_summary.last_tick.unwrap().seq, 1);
+        let quote = apple_summary.quote.unwrap();
+        assert!(quote.bid <= quote.ask);
+        assert_eq!(apple_summary.summary.averages.len(), 3);
+        assert_eq!(apple_summary.summary.averages.get("SMA(20)"), Some(&None));
+
+        let req = test::TestRequest::get()
+            .uri("/summary?stocks=APPL&averages=sma1,wma1")
+            .to_request();
+        let sum_resp: Vec<SummaryResponse> = test::read_response_json(&mut app, req).await;
+        let averages = &sum_resp.first().unwrap().summary.averages;
+        let last_price = sum_resp.first().unwrap().last_tick.unwrap().price;
+        assert_eq!(averages.len(), 2);
+        assert_eq!(averages.get("SMA(1)"), Some(&Some(last_price)));
+        assert_eq!(averages.get("WMA(1)"), Some(&Some(last_price)));
+
+        let req = test::TestRequest::get()
+            .uri("/summary?stocks=APPL&averages=sma1,bogus")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/summary?stocks=APPL,EURUSD&ccy=eur")
+            .to_request();
+        let sum_resp: Vec<SummaryResponse> = test::read_response_json(&mut app, req).await;
+        let euro = app_state.stock_data.read().unwrap().get_rate("USD", "EUR");
+        let dollars = app_state.stock_data.read().unwrap().get_last_price("APPL");
+        assert_eq!(sum_resp[0].currency, "EUR");
+        assert_eq!(
+            sum_resp[0].last_tick.unwrap().price,
+            dollars.unwrap().scaled(euro.unwrap())
+        );
+        assert_eq!(sum_resp[1].last_tick.unwrap().price.to_f64(), 1.0);
+
+        for (query, status) in [("ccy=EURO", 400), ("ccy=CHF", 404)] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/summary?stocks=APPL&{}", query))
+                .to_request();
+            let resp: ServiceResponse = app.call(req).await.unwrap();
+            assert_eq!(resp.status().as_u16(), status);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_symbols() {
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(StockData::initialize())),
+            journal: None,
+        });
+        let user_store = UserStore {
+            users: HashMap::new(),
+            stock_data_sink: app_state.stock_data.clone(),
+        }
+        .start();
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .data(Config::from_vars(&HashMap::new()).unwrap())
+            .data(user_store)
+            .route("/admin/symbols", web::get().to(list_symbols))
+            .route("/admin/symbols", web::post().to(add_symbol))
+            .route("/admin/symbols/{symbol}", web::delete().to(delist_symbol));
+        let mut app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols")
+            .set_json(&SymbolRequest {
+                symbol: "nflx".into(),
+                precision: None,
+            })
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols")
+            .set_json(&SymbolRequest {
+                symbol: "NFLX".into(),
+                precision: None,
+            })
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols")
+            .set_json(&serde_json::json!({ "symbol": "BRK", "precision": 12 }))
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols")
+            .set_json(&serde_json::json!({ "symbol": "BRK", "precision": 0 }))
+            .to_request();
+        let added: SymbolRequest = test::read_response_json(&mut app, req).await;
+        assert_eq!(added.precision, Some(0));
+
+        let req = test::TestRequest::delete()
+            .uri("/admin/symbols/GOOG")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let req = test::TestRequest::delete()
+            .uri("/admin/symbols/GOOG")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/admin/symbols").to_request();
+        let symbols: Vec<String> = test::read_response_json(&mut app, req).await;
+        assert_eq!(
+            symbols,
+            vec!["APPL", "TSLA", "AMZN", "MSFT", "FB", "NFLX", "BRK"]
+        );
+
+        let req = test::TestRequest::delete()
+            .uri("/admin/symbols/nflx")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_candles() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        for second in 0..150 {
+            stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+        }
+
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(stock_data)),
+            journal: None,
+        });
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .route("/candles", web::get().to(get_candles));
+        let mut app = test::init_service(app).await;
+
+        let req = test::TestRequest::get()
+            .uri("/candles?stock=APPL&interval=2m")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/candles?stock=NOPE&interval=1m")
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get()
+            .uri("/candles?stock=APPL&interval=1m")
+            .to_request();
+        let candles: Vec<Candle> = test::read_response_json(&mut app, req).await;
+        assert_eq!(candles.len(), 3);
+        assert_eq!(candles[0].start, 0);
+        // every tick trades at least one share
+        assert!(candles[0].volume >= 60);
+        assert!(candles[0].low <= candles[0].high);
+
+        let req = test::TestRequest::get()
+            .uri("/candles?stock=APPL&interval=1s&limit=10")
+            .to_request();
+        let candles: Vec<Candle> = test::read_response_json(&mut app, req).await;
+        assert_eq!(candles.len(), 10);
+        assert_eq!(candles[9].start, 149_000);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_indicator() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        for _ in 0..20 {
+            stock_data.generate_next_tick(&mut thread_rng);
+        }
+
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(stock_data)),
+            journal: None,
+        });
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .route("/indicators", web::get().to(get_indicator));
+        let mut app = test::init_service(app).await;
+
+        for (uri, status) in [
+            (
+                "/indicators?stock=APPL&name=adx",
+                http::StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/indicators?stock=APPL&name=rsi&period=0",
+                http::StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/indicators?stock=APPL&name=stochastic&period=18446744073709551615",
+                http::StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/indicators?stock=NOPE&name=rsi",
+                http::StatusCode::NOT_FOUND,
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: ServiceResponse = app.call(req).await.unwrap();
+            assert_eq!(resp.status(), status, "{}", uri);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/indicators?stock=APPL&name=rsi&period=14")
+            .to_request();
+        let resp: IndicatorResponse = test::read_response_json(&mut app, req).await;
+        assert_eq!(resp.name, Indicator::Rsi);
+        match resp.value {
+            Some(IndicatorValue::Rsi(rsi)) => assert!((0.0..=100.0).contains(&rsi)),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // not enough data for the default 12/26/9 macd yet
+        let req = test::TestRequest::get()
+            .uri("/indicators?stock=APPL&name=macd")
+            .to_request();
+        let resp: IndicatorResponse = test::read_response_json(&mut app, req).await;
+        assert_eq!(resp.period, 26);
+        assert!(resp.value.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_orders_and_depth() {
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(StockData::initialize())),
+            journal: None,
+        });
+        let user_store = UserStore {
+            users: HashMap::new(),
+            stock_data_sink: app_state.stock_data.clone(),
+        }
+        .start();
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .data(user_store)
+            .route("/orders", web::post().to(submit_order))
+            .route("/orders/{id}", web::delete().to(cancel_order))
+            .route("/depth", web::get().to(get_depth));
+        let mut app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/orders")
+            .set_json(&serde_json::json!({
+                "stock": "APPL", "side": "sell", "type": "limit", "price": 50.0, "quantity": 100
+            }))
+            .to_request();
+        let resting: Execution = test::read_response_json(&mut app, req).await;
+        assert_eq!(resting.status, OrderStatus::Open);
+
+        let req = test::TestRequest::post()
+            .uri("/orders")
+            .set_json(&serde_json::json!({
+                "stock": "APPL", "side": "buy", "type": "market", "quantity": 30
+            }))
+            .to_request();
+        let execution: Execution = test::read_response_json(&mut app, req).await;
+        assert_eq!(execution.status, OrderStatus::Filled);
+        // the trade and the tick keep the decimal places of the stock
+        assert_eq!(execution.trades[0].price.to_string(), "50.00");
+        let last_tick = app_state.stock_data.read().unwrap().get_last_tick("APPL");
+        let last_tick = last_tick.unwrap();
+        assert_eq!(
+            (last_tick.price.to_string(), last_tick.volume),
+            ("50.00".into(), 30)
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/depth?stock=APPL")
+            .to_request();
+        let depth: Depth = test::read_response_json(&mut app, req).await;
+        assert_eq!(depth.asks[0].quantity, 70);
+        assert!(depth.bids.is_empty());
+
+        for (req, status) in [
+            (
+                test::TestRequest::post()
+                    .uri("/orders")
+                    .set_json(&serde_json::json!({
+                        "stock": "APPL", "side": "buy", "type": "limit", "quantity": 30
+                    })),
+                http::StatusCode::BAD_REQUEST,
+            ),
+            (
+                test::TestRequest::post()
+                    .uri("/orders")
+                    .set_json(&serde_json::json!({
+                        "stock": "NOPE", "side": "buy", "type": "market", "quantity": 30
+                    })),
+                http::StatusCode::NOT_FOUND,
+            ),
+            (
+                test::TestRequest::get().uri("/depth?stock=NOPE"),
+                http::StatusCode::NOT_FOUND,
+            ),
+            (
+                test::TestRequest::delete().uri(&format!("/orders/{}", resting.order_id)),
+                http::StatusCode::OK,
+            ),
+            (
+                test::TestRequest::delete().uri(&format!("/orders/{}", resting.order_id)),
+                http::StatusCode::NOT_FOUND,
+            ),
+        ] {
+            let resp: ServiceResponse = app.call(req.to_request()).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_halt_and_resume() {
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(StockData::initialize())),
+            journal: None,
+        });
+        let user_store = UserStore {
+            users: HashMap::new(),
+            stock_data_sink: app_state.stock_data.clone(),
+        }
+        .start();
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .data(user_store)
+            .route("/orders", web::post().to(submit_order))
+            .route("/admin/symbols/{symbol}/halt", web::post().to(halt_symbol))
+            .route(
+                "/admin/symbols/{symbol}/resume",
+                web::post().to(resume_symbol),
+            )
+            .route("/admin/symbols/{symbol}/jump", web::post().to(jump_symbol));
+        let mut app = test::init_service(app).await;
+
+        // paths are compared the way symbols were listed
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols/appl/halt?seconds=60")
+            .to_request();
+        let halt: Halt = test::read_response_json(&mut app, req).await;
+        assert_eq!(halt.until, Some(halt.since + 60_000));
+        assert!(app_state.stock_data.read().unwrap().is_halted("APPL"));
+
+        let order = serde_json::json!({
+            "stock": "APPL", "side": "buy", "type": "market", "quantity": 1
+        });
+        for (req, status) in [
+            (
+                test::TestRequest::post().uri("/orders").set_json(&order),
+                http::StatusCode::CONFLICT,
+            ),
+            (
+                test::TestRequest::post().uri("/admin/symbols/NOPE/halt"),
+                http::StatusCode::NOT_FOUND,
+            ),
+            (
+                test::TestRequest::post().uri("/admin/symbols/appl/resume"),
+                http::StatusCode::OK,
+            ),
+            (
+                test::TestRequest::post().uri("/admin/symbols/APPL/resume"),
+                http::StatusCode::CONFLICT,
+            ),
+            (
+                test::TestRequest::post()
+                    .uri("/admin/symbols/APPL/jump")
+                    .set_json(&serde_json::json!({ "size": -1.5 })),
+                http::StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            let resp: ServiceResponse = app.call(req.to_request()).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+        assert!(!app_state.stock_data.read().unwrap().is_halted("APPL"));
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols/appl/jump")
+            .set_json(&serde_json::json!({ "size": -0.1 }))
+            .to_request();
+        let jump: ScheduledJump = test::read_response_json(&mut app, req).await;
+        assert_eq!(jump.stock, "APPL");
+        let events = app_state
+            .stock_data
+            .write()
+            .unwrap()
+            .generate_next_tick(&mut rand::thread_rng());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, MarketEvent::Jump { stock, .. } if stock == "APPL")));
+    }
+
+    #[actix_rt::test]
+    async fn test_get_session() {
+        let mut stock_data = StockData::initialize();
+        let mut calendar = TradingCalendar::always_open();
+        calendar.weekdays_only = true;
+        stock_data.set_calendar(calendar);
+
+        // 1970-01-03 was a saturday
+        let saturday = 2 * Interval::OneDay.millis();
+        stock_data.generate_next_tick_at(&mut rand::thread_rng(), saturday);
+
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(stock_data)),
+            journal: None,
+        });
+        let app = App::new()
+            .app_data(app_state.clone())
+            .route("/session", web::get().to(get_session));
+        let mut app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/session").to_request();
+        let session: SessionResponse = test::read_response_json(&mut app, req).await;
+        assert_eq!(session.state, SessionState::Closed);
+        assert!(app_state
+            .stock_data
+            .read()
+            .unwrap()
+            .get_last_tick("APPL")
+            .is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_corporate_actions() {
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(StockData::initialize())),
+            journal: None,
+        });
+        let app = App::new()
+            .app_data(app_state.clone())
+            .route("/history", web::get().to(get_history))
+            .route(
+                "/admin/symbols/{symbol}/actions",
+                web::post().to(schedule_action),
+            );
+        let mut app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols/APPL/actions")
+            .set_json(&serde_json::json!({ "type": "split", "ratio": 0 }))
+            .to_request();
+        let resp: ServiceResponse = app.call(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/symbols/appl/actions")
+            .set_json(&serde_json::json!({ "type": "split", "ratio": 4, "at": 2000 }))
+            .to_request();
+        let action: ScheduledAction = test::read_response_json(&mut app, req).await;
+        assert_eq!(action.action, CorporateAction::Split { ratio: 4.0 });
+        assert_eq!(action.stock, "APPL");
+
+        let mut thread_rng = rand::thread_rng();
+        let traded = {
+            let mut stock_data = app_state.stock_data.write().unwrap();
+            stock_data.generate_next_tick_at(&mut thread_rng, 1000);
+            let traded = stock_data.get_last_price("APPL").unwrap();
+            stock_data.generate_next_tick_at(&mut thread_rng, 2000);
+            traded
+        };
+
+        let req = test::TestRequest::get()
+            .uri("/history?stock=APPL")
+            .to_request();
+        let adjusted: HistoryResponse = test::read_response_json(&mut app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/history?stock=APPL&adjusted=false")
+            .to_request();
+        let raw: HistoryResponse = test::read_response_json(&mut app, req).await;
+
+        // prices are decimals, so they come through json exactly
+        assert_eq!(adjusted.adjustments.len(), 1);
+        assert_eq!(raw.ticks[0].price, traded);
+        assert_ne!(adjusted.ticks[0].price, traded);
+        assert_eq!(adjusted.ticks[1].price, raw.ticks[1].price);
+    }
+
+    #[test]
+    fn test_replay() {
+        use config::ReplaySpeed;
+
+        let recording = "NFLX,3000,11\nAMD,2000,5\nNFLX,1000,10\n";
+        let prices = stock::parse_csv(recording.as_bytes()).unwrap();
+        let replay = Replay::new(prices, ReplaySpeed::Max);
+        assert_eq!(replay.stocks(), vec!["NFLX", "AMD"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_export_history() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        for second in 0..1500 {
+            stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+        }
+
+        let app_state = Data::new(AppState {
+            stock_data: Arc::new(RwLock::new(stock_data)),
+            journal: None,
+        });
+        let app = App::new()
+            .app_data(app_state.clone())
+            .route("/history/export", web::get().to(export_history));
+        let mut app = test::init_service(app).await;
+
+        for (uri, status) in [
+            (
+                "/history/export?stocks=APPL,NOPE",
+                http::StatusCode::NOT_FOUND,
+            ),
+            (
+                "/history/export?stocks=APPL&format=xlsx",
+                http::StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/history/export?stocks=APPL&from=2000&to=1000",
+                http::StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp: ServiceResponse = app.call(req).await.unwrap();
+            assert_eq!(resp.status(), status, "{}", uri);
+        }
+
+        // more ticks than fit one chunk, the csv reads back as an import
+        let req = test::TestRequest::get()
+            .uri("/history/export?stocks=APPL,GOOG&from=100000&to=1299000")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let prices = stock::parse_csv(&body[..]).unwrap();
+        assert_eq!(prices.len(), 2 * 1200);
+        assert_eq!(
+            (prices[0].stock.as_str(), prices[0].timestamp),
+            ("APPL", 100_000)
+        );
+        assert_eq!(prices[1199].timestamp, 1_299_000);
+        assert_eq!(prices[1200].stock, "GOOG");
+
+        let req = test::TestRequest::get()
+            .uri("/history/export?stocks=GOOG&format=jsonl&from=1400000")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let ticks: Vec<ExportedTick> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(ticks.len(), 100);
+        assert_eq!(ticks[0].stock, "GOOG");
+        assert_eq!(ticks[99].tick.seq, 1500);
+
+        // one row group per chunk, prices read back exactly
+        let req = test::TestRequest::get()
+            .uri("/history/export?stocks=APPL,GOOG&format=parquet&from=100000&to=1299000")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let path = std::env::temp_dir().join(format!("export-{}.parquet", rand::random::<u64>()));
+        std::fs::write(&path, &body[..]).unwrap();
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 4);
+        let batches: Vec<RecordBatch> = reader.build().unwrap().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            batches.iter().map(RecordBatch::num_rows).sum::<usize>(),
+            2 * 1200
+        );
+
+        let appl = app_state
+            .stock_data
+            .read()
+            .unwrap()
+            .get_raw_ticks("APPL")
+            .unwrap()[100];
+        let symbols = batches[0].column(0).as_any().downcast_ref::<StringArray>();
+        assert_eq!(symbols.unwrap().value(0), "APPL");
+        let prices = batches[0]
+            .column(2)
+            .as_any()
+            .downcast_ref::<Decimal128Array>();
+        assert_eq!(
+            prices.unwrap().value(0),
+            appl.price.units() as i128 * 10_i128.pow(MAX_SCALE - appl.price.scale())
+        );
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", rand::random::<u64>()));
+        assert!(persistence::load_snapshot(&path).unwrap().is_none());
+
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        for second in 0..50 {
+            stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+        }
+        let stock_data = Arc::new(RwLock::new(stock_data));
+
+        persistence::save_snapshot(&stock_data, None, &path).unwrap();
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        assert!(!std::path::Path::new(&temporary).exists());
+
+        // floats read back exactly as they were written
+        let snapshot = persistence::load_snapshot(&path).unwrap().unwrap();
+        assert_eq!(
+            snapshot.stocks,
+            stock_data.read().unwrap().snapshot().stocks
+        );
+
+        std::fs::write(&path, "{").unwrap();
+        let err = persistence::load_snapshot(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal() {
+        let path = std::env::temp_dir().join(format!("journal-{}.log", rand::random::<u64>()));
+        assert!(persistence::read_journal(&path).unwrap().is_empty());
+
+        let mut stock_data = StockData::initialize();
+        let mut journal = Journal::open(&path, &stock_data, 1).unwrap();
+        let mut thread_rng = rand::thread_rng();
+        for second in 0..10 {
+            let events = stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+            journal.append(&stock_data, &events).unwrap();
+        }
+
+        // the listed stocks come first
+        let entries = persistence::read_journal(&path).unwrap();
+        assert_eq!(entries.len(), 6 + 60);
+        let mut recovered = StockData::initialize();
+        for entry in &entries {
+            recovered.apply_journal_entry(entry);
+        }
+        assert_eq!(recovered.snapshot().stocks, stock_data.snapshot().stocks);
+
+        // a line cut short by a crash is dropped, a corrupted one in the middle fails
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("{}0badc0de {{\"type\":", content)).unwrap();
+        assert_eq!(persistence::read_journal(&path).unwrap(), entries);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        content.replace_range(0..8, "00000000");
+        std::fs::write(&path, content).unwrap();
+        let err = persistence::read_journal(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_rotation() {
+        let id = rand::random::<u64>();
+        let path = std::env::temp_dir().join(format!("journal-{}.log", id));
+        let snapshot_path = std::env::temp_dir().join(format!("snapshot-{}.json", id));
+
+        // an archive of an earlier snapshot, only the latest one is kept
+        let stale = std::path::PathBuf::from(format!("{}.1", path.display()));
+        std::fs::write(&stale, "").unwrap();
+
+        let mut stock_data = StockData::initialize();
+        let mut journal = Journal::open(&path, &stock_data, 1).unwrap();
+        let mut thread_rng = rand::thread_rng();
+        for second in 0..5 {
+            let events = stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+            journal.append(&stock_data, &events).unwrap();
+        }
+        stock_data.delist_stock("GOOG").unwrap();
+        journal.record(JournalEntry::Delisted {
+            stock: "GOOG".into(),
+        });
+
+        let stock_data = Arc::new(RwLock::new(stock_data));
+        let journal = Arc::new(Mutex::new(journal));
+        persistence::save_snapshot(&stock_data, Some(&journal), &snapshot_path).unwrap();
+        for second in 5..8 {
+            let mut stock_data = stock_data.write().unwrap();
+            let events = stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+            journal
+                .lock()
+                .unwrap()
+                .append(&stock_data, &events)
+                .unwrap();
+        }
+
+        // the journal keeps what came after the snapshot, the archive what the snapshot holds
+        let snapshot = persistence::load_snapshot(&snapshot_path).unwrap().unwrap();
+        let entries = persistence::read_journal(&path).unwrap();
+        assert_eq!(entries.len(), 3 * 5);
+        let archive = std::path::PathBuf::from(format!("{}.{}", path.display(), snapshot.taken_at));
+        let archived = persistence::read_journal(&archive).unwrap();
+        assert_eq!(archived.len(), 6 + 5 * 6 + 2);
+        assert!(!stale.exists());
+
+        // replaying the whole journal, as left by a crash while rotating, skips what the snapshot holds
+        let mut recovered = StockData::initialize();
+        recovered.restore(&snapshot).unwrap();
+        let journal = archived.into_iter().chain(entries).collect();
+        for entry in persistence::since_snapshot(journal, snapshot.taken_at) {
+            recovered.apply_journal_entry(&entry);
+        }
+        assert_eq!(
+            recovered.snapshot().stocks,
+            stock_data.read().unwrap().snapshot().stocks
+        );
+        assert!(!recovered.get_stocks().contains(&"GOOG".to_string()));
+
+        for path in [path, snapshot_path, archive] {
+            std::fs::remove_file(path).unwrap();
+        }
     }
 }
@@ -1,4 +1,5 @@
 use actix::{Addr, Message};
+use stock::{Adjustment, Currency, Halt, SessionState};
 
 use crate::actors::socket_session::SocketSession;
 
@@ -19,9 +20,51 @@ pub(crate) struct SendClientMessage {
     pub message: String,
 }
 
+/// what a subscription streams, ticks come from `/subscribe`, quotes from `/quotes`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum SubscriptionKind {
+    Ticks,
+    Quotes,
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub(crate) struct UpdateUserSubscriptions {
     pub subscriptions: Vec<String>,
+    pub kind: SubscriptionKind,
+    /// currency to convert the prices to, the one each stock is quoted in if none
+    pub currency: Option<Currency>,
     pub user_id: usize,
 }
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StockDelisted {
+    pub stock: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StockHalted {
+    pub stock: String,
+    pub halt: Halt,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StockResumed {
+    pub stock: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StockAdjusted {
+    pub stock: String,
+    pub adjustment: Adjustment,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct MarketSessionChanged {
+    pub state: SessionState,
+}
@@ -0,0 +1,359 @@
+use actix::clock::{interval_at, Instant};
+use actix_web::web;
+use futures::StreamExt;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use stock::{JournalEntry, MarketEvent, Snapshot, StockData, Symbol, Timestamp};
+
+use crate::state::StockDataSink;
+
+pub(crate) type JournalSink = Arc<Mutex<Journal>>;
+
+/// reads the snapshot at `path`, None if there is none yet
+pub(crate) fn load_snapshot(path: &Path) -> io::Result<Option<Snapshot>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    serde_json::from_reader(BufReader::new(file))
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// snapshots the stock data to `path`, the snapshot is written to a temporary file
+/// which then replaces `path`, so a crash midway leaves the previous snapshot in place,
+/// once the snapshot is in place the journal only keeps what came after it
+pub(crate) fn save_snapshot(
+    stock_data: &StockDataSink,
+    journal: Option<&JournalSink>,
+    path: &Path,
+) -> io::Result<()> {
+    // only copying the data holds the lock, writing it out does not
+    let (snapshot, journaled) = {
+        let stock_data = stock_data.read().unwrap();
+        let snapshot = stock_data.snapshot();
+        let journaled = match journal {
+            Some(journal) => Some(journal.lock().unwrap().mark_snapshot(snapshot.taken_at)?),
+            None => None,
+        };
+        (snapshot, journaled)
+    };
+
+    let temporary = temporary_path(path);
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    serde_json::to_writer(&mut writer, &snapshot)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+
+    fs::rename(&temporary, path)?;
+    sync_directory(path)?;
+
+    match (journal, journaled) {
+        (Some(journal), Some(journaled)) => {
+            journal.lock().unwrap().rotate(journaled, snapshot.taken_at)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// snapshots the stock data every `period`, off the actor threads
+pub(crate) async fn snapshot_every(
+    stock_data: StockDataSink,
+    journal: Option<JournalSink>,
+    path: PathBuf,
+    period: Duration,
+) {
+    let mut task = interval_at(Instant::now() + period, period);
+
+    while task.next().await.is_some() {
+        let stock_data = stock_data.clone();
+        let journal = journal.clone();
+        let path = path.clone();
+        // a failed snapshot leaves the previous one in place, the next one tries again
+        let _ = web::block(move || save_snapshot(&stock_data, journal.as_ref(), &path)).await;
+    }
+}
+
+/// makes the renames within the directory of `path` durable
+fn sync_directory(path: &Path) -> io::Result<()> {
+    File::open(directory_of(path))?.sync_all()
+}
+
+/// directory `path` is in
+fn directory_of(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// `path` with `.tmp` appended, in the same directory so the rename stays atomic
+fn temporary_path(path: &Path) -> PathBuf {
+    suffixed(path, "tmp")
+}
+
+/// `path` with `.suffix` appended
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut suffixed = OsString::from(path.as_os_str());
+    suffixed.push(".");
+    suffixed.push(suffix);
+    PathBuf::from(suffixed)
+}
+
+/// Journal
+/// append-only record of every tick published, every corporate action and every listing,
+/// one line of `checksum json` per entry where the checksum is the crc32 of the json in hex,
+/// what a snapshot holds is moved out to an archive next to it once the snapshot is written
+#[derive(Debug)]
+pub(crate) struct Journal {
+    path: PathBuf,
+    file: File,
+    /// archives kept next to the journal, older ones are deleted
+    archives: usize,
+    /// sequence number of the last tick journaled per stock
+    journaled: HashMap<Symbol, u64>,
+    /// entries other than ticks that did not make it into the journal yet
+    pending: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// opens the journal at `path` for appending, creating it if needed, and journals
+    /// the stocks listed on startup, every tick the stock data already holds counts as journaled
+    pub fn open(path: &Path, stock_data: &StockData, archives: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut journal = Self {
+            path: path.into(),
+            file,
+            archives,
+            journaled: HashMap::new(),
+            pending: vec![],
+        };
+
+        for stock in stock_data.get_stocks() {
+            journal.record(JournalEntry::Listed {
+                stock: stock.clone(),
+                precision: stock_data.get_precision(stock),
+            });
+            let seq = stock_data.get_last_tick(stock).map_or(0, |tick| tick.seq);
+            journal.journaled.insert(stock.clone(), seq);
+        }
+        journal.append(stock_data, &[])?;
+        Ok(journal)
+    }
+
+    /// journals a listing, delisting or scheduled jump or action with the next append,
+    /// the ticks of a stock listed anew are journaled from its first one
+    pub fn record(&mut self, entry: JournalEntry) {
+        match &entry {
+            JournalEntry::Listed { stock, .. } => {
+                self.journaled.insert(stock.clone(), 0);
+            }
+            JournalEntry::Delisted { stock } => {
+                self.journaled.remove(stock);
+            }
+            _ => (),
+        }
+        self.pending.push(entry);
+    }
+
+    /// appends the corporate actions among `events` and then every tick recorded since
+    /// the last append, returns once they are synced to disk, whatever fails to be written
+    /// is tried again with the next append
+    pub fn append(&mut self, stock_data: &StockData, events: &[MarketEvent]) -> io::Result<()> {
+        for event in events {
+            if let MarketEvent::CorporateAction { stock, adjustment } = event {
+                self.pending.push(JournalEntry::Adjustment {
+                    stock: stock.clone(),
+                    adjustment: *adjustment,
+                });
+            }
+        }
+
+        let mut entries = self.pending.clone();
+        let mut journaled = HashMap::new();
+        for stock in stock_data.get_stocks() {
+            let seq = self.journaled.get(stock).copied().unwrap_or(0);
+            for tick in stock_data
+                .get_raw_ticks_since(stock, seq)
+                .unwrap_or_default()
+            {
+                journaled.insert(stock.clone(), tick.seq);
+                entries.push(JournalEntry::Tick {
+                    stock: stock.clone(),
+                    tick,
+                });
+            }
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        self.write(&entries)?;
+        self.pending.clear();
+        self.journaled.extend(journaled);
+        Ok(())
+    }
+
+    /// journals the pending entries and marks that a snapshot is taken at `taken_at`,
+    /// returns how long the journal is up to the mark
+    pub fn mark_snapshot(&mut self, taken_at: Timestamp) -> io::Result<u64> {
+        let mut entries = self.pending.clone();
+        entries.push(JournalEntry::SnapshotTaken { taken_at });
+        self.write(&entries)?;
+        self.pending.clear();
+        self.file.metadata().map(|metadata| metadata.len())
+    }
+
+    /// moves the first `len` bytes of the journal, held by the snapshot taken at `taken_at`,
+    /// to `path.taken_at` and keeps the rest, a crash midway leaves the journal as it was,
+    /// then deletes the archives beyond the ones to keep
+    pub fn rotate(&mut self, len: u64, taken_at: Timestamp) -> io::Result<()> {
+        let mut journal = File::open(&self.path)?;
+        if self.archives > 0 {
+            let mut archive = File::create(suffixed(&self.path, &taken_at.to_string()))?;
+            io::copy(&mut (&mut journal).take(len), &mut archive)?;
+            archive.sync_all()?;
+        }
+
+        let temporary = temporary_path(&self.path);
+        let mut rest = File::create(&temporary)?;
+        journal.seek(SeekFrom::Start(len))?;
+        io::copy(&mut journal, &mut rest)?;
+        rest.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        sync_directory(&self.path)?;
+
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.prune_archives()
+    }
+
+    /// deletes all but the latest `archives` archives of the journal
+    fn prune_archives(&self) -> io::Result<()> {
+        let prefix = match self.path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("{}.", name),
+            None => return Ok(()),
+        };
+        let mut archives: Vec<(Timestamp, PathBuf)> = fs::read_dir(directory_of(&self.path))?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                let taken_at = name.strip_prefix(&prefix)?.parse().ok()?;
+                Some((taken_at, entry.path()))
+            })
+            .collect();
+        archives.sort();
+
+        let stale = archives.len().saturating_sub(self.archives);
+        for (_, path) in &archives[..stale] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// appends the entries and syncs them to disk, a write that fails midway is cut off
+    /// again so the journal never holds half a line
+    fn write(&mut self, entries: &[JournalEntry]) -> io::Result<()> {
+        let mut lines = String::new();
+        for entry in entries {
+            let json = serde_json::to_string(entry)?;
+            lines.push_str(&format!("{:08x} {}\n", checksum(&json), json));
+        }
+
+        let len = self.file.metadata()?.len();
+        let written = self
+            .file
+            .write_all(lines.as_bytes())
+            .and_then(|_| self.file.sync_data());
+        if written.is_err() {
+            let _ = self.file.set_len(len);
+        }
+        written
+    }
+}
+
+/// journals what was recorded since the last append, false if it could not be written,
+/// the ticks are then held back from UserStore until a later append gets them in
+pub(crate) fn append(
+    journal: Option<&JournalSink>,
+    stock_data: &StockData,
+    events: &[MarketEvent],
+) -> bool {
+    match journal {
+        Some(journal) => journal.lock().unwrap().append(stock_data, events).is_ok(),
+        None => true,
+    }
+}
+
+/// entries of the journal that came after the snapshot taken at `taken_at`,
+/// all of them if the snapshot was never marked in the journal
+pub(crate) fn since_snapshot(entries: Vec<JournalEntry>, taken_at: Timestamp) -> Vec<JournalEntry> {
+    let marked = entries.iter().rposition(
+        |entry| matches!(entry, JournalEntry::SnapshotTaken { taken_at: at } if *at == taken_at),
+    );
+    match marked {
+        Some(index) => entries[index + 1..].to_vec(),
+        None => entries,
+    }
+}
+
+/// reads every entry of the journal at `path`, none if there is no journal yet,
+/// a last line cut short by a crash is dropped from the file, any other line
+/// that does not check out fails
+pub(crate) fn read_journal(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let mut entries = vec![];
+    let mut valid = 0;
+    for (index, line) in content.split_inclusive(|byte| *byte == b'\n').enumerate() {
+        match parse_journal_line(line) {
+            Some(entry) => {
+                entries.push(entry);
+                valid += line.len();
+            }
+            None if valid + line.len() == content.len() => {
+                OpenOptions::new()
+                    .write(true)
+                    .open(path)?
+                    .set_len(valid as u64)?;
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("journal line {} is corrupted", index + 1),
+                ))
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// a complete line whose checksum matches its entry
+fn parse_journal_line(line: &[u8]) -> Option<JournalEntry> {
+    let line = std::str::from_utf8(line.strip_suffix(b"\n")?).ok()?;
+    let (expected, json) = line.split_once(' ')?;
+
+    if u32::from_str_radix(expected, 16).ok()? != checksum(json) {
+        return None;
+    }
+    serde_json::from_str(json).ok()
+}
+
+fn checksum(json: &str) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(json.as_bytes());
+    hasher.finalize()
+}
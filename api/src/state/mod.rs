@@ -1,20 +1,129 @@
 use actix_web::web::Data;
-use std::sync::{Arc, RwLock};
-use stock::StockData;
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
+use stock::{
+    parse_csv, parse_spread_model, parse_trend_detector, CorrelationMatrix, JournalEntry, StockData,
+};
+
+use crate::{
+    config::{Config, PriceModelKind},
+    persistence::{append, load_snapshot, read_journal, since_snapshot, Journal, JournalSink},
+};
 
 pub(crate) type StockDataSink = Arc<RwLock<StockData>>;
 
 #[derive(Debug)]
 pub(crate) struct AppState {
     pub stock_data: StockDataSink,
+    /// journal of what is published, if there is one
+    pub journal: Option<JournalSink>,
 }
 
 impl AppState {
-    pub fn new() -> Data<Self> {
-        let stock_data = StockData::initialize();
+    /// applies the config to fresh stock data restored from the last snapshot and the journal
+    /// if there are any and opens the journal for appending, fails if they or the prices
+    /// to import can not be read or the correlation does not fit the listed stocks
+    pub fn new(config: &Config) -> io::Result<Data<Self>> {
+        let mut stock_data = StockData::with_retention(config.retention);
+        stock_data.set_averages(config.averages.clone());
+        stock_data.set_trend_detector(
+            parse_trend_detector(&config.trend_detector)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+        );
+        stock_data.set_spread_model(
+            parse_spread_model(&config.spread_model)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+        );
+        stock_data.set_calendar(config.calendar.clone());
+        if let Some(precision) = &config.precision {
+            stock_data.set_default_precision(precision.default);
+        }
+
+        let mut taken_at = None;
+        if let Some(path) = &config.snapshot {
+            if let Some(snapshot) = load_snapshot(Path::new(path))? {
+                stock_data
+                    .restore(&snapshot)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                taken_at = Some(snapshot.taken_at);
+            }
+        }
+        if let Some(path) = &config.journal {
+            let entries = read_journal(Path::new(path))?;
+            // what the snapshot holds is only left in the journal after a crash while rotating it
+            let entries = match taken_at {
+                Some(taken_at) => since_snapshot(entries, taken_at),
+                None => entries,
+            };
+            for entry in &entries {
+                stock_data.apply_journal_entry(entry);
+            }
+        }
+
+        if let Some(path) = &config.import_csv {
+            let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+            let prices = parse_csv(BufReader::new(File::open(path)?)).map_err(invalid)?;
+            stock_data
+                .import_prices(prices, config.import_mode)
+                .map_err(invalid)?;
+        }
+
+        for pair in &config.fx_pairs {
+            // a symbol that is taken by something else than a currency is left alone
+            let _ = stock_data.add_fx_pair(pair);
+        }
+
+        for stock in stock_data.get_stocks().to_vec() {
+            let is_fx_pair = stock_data.get_fx_pair(&stock).is_some();
+            if let Some(precision) = &config.precision {
+                // fx rates keep their finer precision unless named
+                if !is_fx_pair || precision.symbols.contains_key(&stock) {
+                    stock_data.set_precision(&stock, precision.of(&stock));
+                }
+            }
+            if let Some(currencies) = &config.currencies {
+                stock_data.set_currency(&stock, &currencies.of(&stock));
+            }
+            if config.price_model != PriceModelKind::Uniform && !is_fx_pair {
+                stock_data.set_price_model(&stock, config.price_model.build(&stock));
+            }
+        }
 
-        Data::new(Self {
+        stock_data.set_random_jumps(config.random_jumps);
+        stock_data.set_circuit_breaker(config.circuit_breaker);
+
+        if let Some(spec) = &config.correlation {
+            let shocks = CorrelationMatrix::parse(stock_data.get_stocks(), spec)
+                .and_then(|matrix| matrix.cholesky())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            stock_data.set_correlation(Some(shocks));
+        }
+
+        let journal = match &config.journal {
+            Some(path) => Some(Arc::new(Mutex::new(Journal::open(
+                Path::new(path),
+                &stock_data,
+                config.journal_archives,
+            )?))),
+            None => None,
+        };
+
+        Ok(Data::new(Self {
             stock_data: Arc::new(RwLock::new(stock_data)),
-        })
+            journal,
+        }))
+    }
+
+    /// journals a listing, delisting or scheduled jump or action along with the ticks
+    /// recorded since the last append, whatever fails to be written goes out with the next append
+    pub fn record(&self, stock_data: &StockData, entry: JournalEntry) {
+        if let Some(journal) = &self.journal {
+            journal.lock().unwrap().record(entry);
+            append(Some(journal), stock_data, &[]);
+        }
     }
 }
@@ -0,0 +1,59 @@
+//! measures the cost of a tick right after start up and again after a million ticks,
+//! run with `cargo bench -p stock`
+
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+use stock::StockData;
+
+const TICKS: u64 = 1_000_000;
+const SAMPLE: u64 = 10_000;
+/// how much slower the late ticks may be before the cost counts as growing
+const MAX_RATIO: f64 = 2.0;
+
+/// median time per tick over a few samples of `SAMPLE` ticks
+fn time_per_tick(stock_data: &mut StockData, rng: &mut StdRng, second: &mut u64) -> Duration {
+    let mut samples: Vec<Duration> = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..SAMPLE {
+                stock_data.generate_next_tick_at(rng, *second * 1000);
+                *second += 1;
+            }
+            black_box(stock_data.get_summaries());
+            start.elapsed() / SAMPLE as u32
+        })
+        .collect();
+
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+fn main() {
+    let mut stock_data = StockData::initialize();
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut second = 0;
+
+    let early = time_per_tick(&mut stock_data, &mut rng, &mut second);
+
+    while second < TICKS {
+        stock_data.generate_next_tick_at(&mut rng, second * 1000);
+        second += 1;
+    }
+
+    let late = time_per_tick(&mut stock_data, &mut rng, &mut second);
+    let ratio = late.as_secs_f64() / early.as_secs_f64();
+
+    println!("stocks:             {}", stock_data.get_stocks().len());
+    println!("first ticks:        {:?} per tick", early);
+    println!("after {} ticks: {:?} per tick", TICKS, late);
+    println!("ratio:              {:.2}", ratio);
+
+    assert!(
+        ratio < MAX_RATIO,
+        "tick cost grew {:.2}x with history",
+        ratio
+    );
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Price, Symbol, Timestamp};
+
+/// split or cash dividend of a stock
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// every share becomes `ratio` shares, 2 is a 2-for-1 split
+    Split { ratio: f64 },
+    /// cash paid out per share
+    Dividend { amount: Price },
+}
+
+impl CorporateAction {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            CorporateAction::Split { ratio } => ratio.is_finite() && *ratio > 0.0,
+            CorporateAction::Dividend { amount } => amount.is_positive(),
+        }
+    }
+
+    /// what the action does to the history of a stock last traded at `last_price`,
+    /// None for a dividend that would leave nothing of the price
+    pub fn adjustment(&self, ex_date: Timestamp, last_price: Price) -> Option<Adjustment> {
+        let (price_factor, volume_factor) = match *self {
+            CorporateAction::Split { ratio } => (1.0 / ratio, ratio),
+            CorporateAction::Dividend { amount } if amount < last_price => {
+                ((last_price - amount).to_f64() / last_price.to_f64(), 1.0)
+            }
+            CorporateAction::Dividend { .. } => return None,
+        };
+
+        Some(Adjustment {
+            ex_date,
+            action: *self,
+            price_factor,
+            volume_factor,
+        })
+    }
+}
+
+impl fmt::Display for CorporateAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorporateAction::Split { ratio } => write!(f, "split {}:1", ratio),
+            CorporateAction::Dividend { amount } => write!(f, "dividend {}", amount),
+        }
+    }
+}
+
+/// corporate action applied to the first tick of a stock at or after `at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledAction {
+    pub stock: Symbol,
+    pub at: Timestamp,
+    #[serde(flatten)]
+    pub action: CorporateAction,
+}
+
+/// corporate action that took effect, everything recorded before `ex_date` had its prices
+/// multiplied by `price_factor` and its volumes by `volume_factor`
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Adjustment {
+    pub ex_date: Timestamp,
+    pub action: CorporateAction,
+    pub price_factor: f64,
+    pub volume_factor: f64,
+}
+
+impl Adjustment {
+    /// adjusted price, rounded to the decimal places of `price`
+    pub fn price(&self, price: Price) -> Price {
+        price.scaled(self.price_factor)
+    }
+
+    pub fn volume(&self, volume: u64) -> u64 {
+        (volume as f64 * self.volume_factor).round() as u64
+    }
+
+    /// combined factors of the adjustments that took effect after a timestamp,
+    /// dividing an adjusted value by them gives back what was recorded at the time
+    pub fn since(adjustments: &[Adjustment], timestamp: Timestamp) -> (f64, f64) {
+        adjustments
+            .iter()
+            .filter(|adjustment| adjustment.ex_date > timestamp)
+            .fold((1.0, 1.0), |(price, volume), adjustment| {
+                (
+                    price * adjustment.price_factor,
+                    volume * adjustment.volume_factor,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjustments() {
+        let split = CorporateAction::Split { ratio: 2.0 };
+        let split = split.adjustment(1_000, Price::new(100, 0)).unwrap();
+        assert_eq!(
+            (split.price(Price::new(10001, 2)), split.volume(15)),
+            (Price::new(5001, 2), 30)
+        );
+
+        let dividend = CorporateAction::Dividend {
+            amount: Price::new(5, 0),
+        };
+        let dividend = dividend.adjustment(2_000, Price::new(50, 0)).unwrap();
+        assert_eq!(dividend.price(Price::new(5000, 2)), Price::new(4500, 2));
+        assert_eq!(dividend.volume(15), 15);
+
+        assert!(CorporateAction::Dividend {
+            amount: Price::new(50, 0)
+        }
+        .adjustment(3_000, Price::new(50, 0))
+        .is_none());
+        assert!(!CorporateAction::Split { ratio: 0.0 }.is_valid());
+
+        let adjustments = [split, dividend];
+        assert_eq!(Adjustment::since(&adjustments, 500), (0.45, 2.0));
+        assert_eq!(Adjustment::since(&adjustments, 1_000), (0.9, 1.0));
+        assert_eq!(Adjustment::since(&adjustments, 2_000), (1.0, 1.0));
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{
+    rolling::RollingWindow,
+    utils::{exponential_moving_average, simple_moving_average, weighted_moving_average},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AverageKind {
+    Simple,
+    Exponential,
+    Weighted,
+}
+
+/// windowed moving average, written as `sma20`, `ema50` or `wma10`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MovingAverage {
+    pub kind: AverageKind,
+    pub window: usize,
+}
+
+impl MovingAverage {
+    /// averages reported in every summary unless configured otherwise
+    pub const DEFAULTS: [MovingAverage; 3] = [
+        MovingAverage::new(AverageKind::Simple, 20),
+        MovingAverage::new(AverageKind::Exponential, 50),
+        MovingAverage::new(AverageKind::Weighted, 10),
+    ];
+
+    pub const fn new(kind: AverageKind, window: usize) -> Self {
+        Self { kind, window }
+    }
+
+    /// computes the average over the given prices, None until there are `window` prices
+    pub fn compute(&self, prices: &[f64]) -> Option<f64> {
+        match self.kind {
+            AverageKind::Simple => simple_moving_average(prices, self.window),
+            AverageKind::Exponential => exponential_moving_average(prices, self.window),
+            AverageKind::Weighted => weighted_moving_average(prices, self.window),
+        }
+    }
+}
+
+impl fmt::Display for MovingAverage {
+    /// formats as `SMA(20)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            AverageKind::Simple => "SMA",
+            AverageKind::Exponential => "EMA",
+            AverageKind::Weighted => "WMA",
+        };
+        write!(f, "{}({})", kind, self.window)
+    }
+}
+
+impl FromStr for MovingAverage {
+    type Err = String;
+
+    /// parses `sma20`, `EMA50`, `wma(10)` and so on
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let invalid = || format!("invalid moving average {:?}", s);
+
+        if s.len() < 4 {
+            return Err(invalid());
+        }
+
+        let (kind, window) = s.split_at(3);
+        let kind = match kind {
+            "sma" => AverageKind::Simple,
+            "ema" => AverageKind::Exponential,
+            "wma" => AverageKind::Weighted,
+            _ => return Err(invalid()),
+        };
+        let window = window
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .parse::<usize>()
+            .map_err(|_| invalid())?;
+
+        if window == 0 {
+            return Err(invalid());
+        }
+
+        Ok(Self::new(kind, window))
+    }
+}
+
+/// Average Tracker
+/// keeps a moving average up to date one price at a time, in constant time per price
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AverageTracker {
+    average: MovingAverage,
+    window: RollingWindow,
+    ema: Option<f64>,
+}
+
+impl AverageTracker {
+    pub fn new(average: MovingAverage) -> Self {
+        Self {
+            average,
+            window: RollingWindow::new(average.window),
+            ema: None,
+        }
+    }
+
+    pub fn average(&self) -> MovingAverage {
+        self.average
+    }
+
+    pub fn push(&mut self, price: f64) {
+        self.window.push(price);
+
+        // seeded with the simple average of the first full window, like the batch ema
+        if self.average.kind == AverageKind::Exponential && self.window.is_full() {
+            let alpha = 2.0 / (self.average.window as f64 + 1.0);
+            self.ema = match self.ema {
+                Some(ema) => Some(alpha * price + (1.0 - alpha) * ema),
+                None => self.window.mean(),
+            };
+        }
+    }
+
+    /// current value, None until there are `window` prices
+    pub fn value(&self) -> Option<f64> {
+        let window = self.average.window;
+        if window == 0 || !self.window.is_full() {
+            return None;
+        }
+
+        match self.average.kind {
+            AverageKind::Simple => self.window.mean(),
+            AverageKind::Exponential => self.ema,
+            AverageKind::Weighted => {
+                Some(self.window.weighted_sum() / (window * (window + 1) / 2) as f64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moving_average_parsing() {
+        assert_eq!(
+            "sma20".parse(),
+            Ok(MovingAverage::new(AverageKind::Simple, 20))
+        );
+        assert_eq!(
+            "EMA(50)".parse(),
+            Ok(MovingAverage::new(AverageKind::Exponential, 50))
+        );
+        assert_eq!(
+            " wma10 ".parse(),
+            Ok(MovingAverage::new(AverageKind::Weighted, 10))
+        );
+        assert!("sma".parse::<MovingAverage>().is_err());
+        assert!("sma0".parse::<MovingAverage>().is_err());
+        assert!("xma10".parse::<MovingAverage>().is_err());
+
+        assert_eq!(MovingAverage::DEFAULTS[0].to_string(), "SMA(20)");
+    }
+
+    #[test]
+    fn test_average_tracker() {
+        let prices: Vec<f64> = (0..500)
+            .map(|v| 100.0 + (v as f64 * 0.7).sin() * 10.0)
+            .collect();
+
+        for average in &["sma20", "ema50", "wma10", "sma1"] {
+            let average: MovingAverage = average.parse().unwrap();
+            let mut tracker = AverageTracker::new(average);
+
+            for (i, price) in prices.iter().enumerate() {
+                tracker.push(*price);
+
+                match (tracker.value(), average.compute(&prices[..=i])) {
+                    (Some(value), Some(expected)) => {
+                        assert!((value - expected).abs() < 1e-9, "{}", average)
+                    }
+                    (value, expected) => assert_eq!(value, expected, "{}", average),
+                }
+            }
+        }
+    }
+}
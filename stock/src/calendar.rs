@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt};
+
+use crate::{Adjustment, Price, Timestamp};
+
+const MINUTE: i64 = 60_000;
+const DAY: i64 = 24 * 60 * MINUTE;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const SUNDAY: i64 = 6;
+
+/// part of the trading day the market is in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    PreMarket,
+    Regular,
+    AfterHours,
+    Closed,
+}
+
+impl SessionState {
+    /// pre-market and after-hours trade, but thinner than the regular session
+    pub fn is_extended(&self) -> bool {
+        matches!(self, SessionState::PreMarket | SessionState::AfterHours)
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionState::PreMarket => f.write_str("pre_market"),
+            SessionState::Regular => f.write_str("regular"),
+            SessionState::AfterHours => f.write_str("after_hours"),
+            SessionState::Closed => f.write_str("closed"),
+        }
+    }
+}
+
+/// Trading Calendar
+/// market hours of a trading day, in minutes after local midnight, along with
+/// the days the market stays closed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingCalendar {
+    /// minutes local time is ahead of utc, -300 for new york winter time
+    pub utc_offset: i64,
+    /// clocks go an hour ahead of `utc_offset` from 2:00 on the second sunday in march
+    /// to 2:00 on the first sunday in november, as they do in the us
+    pub us_daylight_saving: bool,
+    pub pre_market: u32,
+    pub regular_open: u32,
+    pub regular_close: u32,
+    pub after_hours_close: u32,
+    /// closes on saturdays and sundays
+    pub weekdays_only: bool,
+    /// fraction of the regular ticks that happen in pre-market and after-hours
+    pub extended_rate: f64,
+    /// local days since the unix epoch
+    holidays: BTreeSet<i64>,
+}
+
+impl Default for TradingCalendar {
+    fn default() -> Self {
+        Self::always_open()
+    }
+}
+
+impl TradingCalendar {
+    /// one regular session per utc day, every day
+    pub fn always_open() -> Self {
+        Self {
+            utc_offset: 0,
+            us_daylight_saving: false,
+            pre_market: 0,
+            regular_open: 0,
+            regular_close: MINUTES_PER_DAY,
+            after_hours_close: MINUTES_PER_DAY,
+            weekdays_only: false,
+            extended_rate: 0.25,
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// new york hours on weekdays, pre-market from 4:00, regular 9:30 to 16:00 and
+    /// after-hours until 20:00, utc-5 in winter and utc-4 in summer
+    pub fn nyse() -> Self {
+        Self {
+            utc_offset: -5 * 60,
+            us_daylight_saving: true,
+            pre_market: 4 * 60,
+            regular_open: 9 * 60 + 30,
+            regular_close: 16 * 60,
+            after_hours_close: 20 * 60,
+            weekdays_only: true,
+            ..Self::always_open()
+        }
+    }
+
+    /// builds a calendar from `always`, `nyse` or `pre,open,close,after_hours_close,utc_offset`
+    /// like `04:00,09:30,16:00,20:00,-300`, custom hours only trade on weekdays
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid market hours {:?}", spec);
+
+        match spec.trim().to_lowercase().as_str() {
+            "always" => return Ok(Self::always_open()),
+            "nyse" => return Ok(Self::nyse()),
+            _ => (),
+        }
+
+        let parts: Vec<&str> = spec.split(',').map(str::trim).collect();
+        let (hours, utc_offset) = match parts.as_slice() {
+            [pre, open, close, after, offset] => (
+                [pre, open, close, after]
+                    .iter()
+                    .map(|time| parse_time(time).ok_or_else(invalid))
+                    .collect::<Result<Vec<u32>, _>>()?,
+                offset.parse::<i64>().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+        if hours.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            utc_offset,
+            pre_market: hours[0],
+            regular_open: hours[1],
+            regular_close: hours[2],
+            after_hours_close: hours[3],
+            weekdays_only: true,
+            ..Self::always_open()
+        })
+    }
+
+    /// closes the market on a local date like `2026-12-25`
+    pub fn add_holiday(&mut self, date: &str) -> Result<(), String> {
+        let invalid = || format!("invalid date {:?}", date);
+        let parts = date
+            .trim()
+            .split('-')
+            .map(str::parse::<i64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+
+        match parts.as_slice() {
+            [year, month, day] if (1..=12).contains(month) && (1..=31).contains(day) => {
+                self.holidays.insert(days_from_civil(*year, *month, *day));
+                Ok(())
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// local day a timestamp falls on, in days since the unix epoch
+    pub fn trading_day(&self, timestamp: Timestamp) -> i64 {
+        self.local(timestamp).0
+    }
+
+    pub fn is_trading_day(&self, day: i64) -> bool {
+        let weekend = weekday(day) >= 5;
+        !self.holidays.contains(&day) && (!self.weekdays_only || !weekend)
+    }
+
+    /// state of the market at a given timestamp
+    pub fn state_at(&self, timestamp: Timestamp) -> SessionState {
+        let (day, minute) = self.local(timestamp);
+
+        if !self.is_trading_day(day) {
+            SessionState::Closed
+        } else if (self.regular_open..self.regular_close).contains(&minute) {
+            SessionState::Regular
+        } else if (self.pre_market..self.regular_open).contains(&minute) {
+            SessionState::PreMarket
+        } else if (self.regular_close..self.after_hours_close).contains(&minute) {
+            SessionState::AfterHours
+        } else {
+            SessionState::Closed
+        }
+    }
+
+    /// local day and minute of the day
+    fn local(&self, timestamp: Timestamp) -> (i64, u32) {
+        let local = timestamp as i64 + self.offset_at(timestamp) * MINUTE;
+        (
+            local.div_euclid(DAY),
+            (local.rem_euclid(DAY) / MINUTE) as u32,
+        )
+    }
+
+    /// minutes local time is ahead of utc at a given timestamp
+    fn offset_at(&self, timestamp: Timestamp) -> i64 {
+        if !self.us_daylight_saving {
+            return self.utc_offset;
+        }
+
+        // the switches happen at 2:00 local time, 2:00 standard time in march
+        // and 2:00 daylight saving time, so 1:00 standard time, in november
+        let standard = timestamp as i64 + self.utc_offset * MINUTE;
+        let year = year_of(standard.div_euclid(DAY));
+        let starts = nth_sunday(year, 3, 2) * DAY + 2 * 60 * MINUTE;
+        let ends = nth_sunday(year, 11, 1) * DAY + 60 * MINUTE;
+
+        if (starts..ends).contains(&standard) {
+            self.utc_offset + 60
+        } else {
+            self.utc_offset
+        }
+    }
+}
+
+/// day of the week of a day since the unix epoch, monday is 0
+fn weekday(day: i64) -> i64 {
+    // the unix epoch was a thursday
+    (day + 3).rem_euclid(7)
+}
+
+/// year a day since the unix epoch falls in
+fn year_of(day: i64) -> i64 {
+    let year = 1970 + day.div_euclid(366);
+    (year..)
+        .take_while(|year| days_from_civil(*year, 1, 1) <= day)
+        .last()
+        .unwrap_or(year)
+}
+
+/// day since the unix epoch of the `nth` sunday of a month
+fn nth_sunday(year: i64, month: i64, nth: i64) -> i64 {
+    let first = days_from_civil(year, month, 1);
+    first + (SUNDAY - weekday(first)).rem_euclid(7) + (nth - 1) * 7
+}
+
+/// `HH:MM` as minutes after midnight, `24:00` is the end of the day
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes) = match (hours.parse::<u32>(), minutes.len(), minutes.parse::<u32>()) {
+        (Ok(hours), 2, Ok(minutes)) => (hours, minutes),
+        _ => return None,
+    };
+
+    if (hours < 24 && minutes < 60) || (hours == 24 && minutes == 0) {
+        Some(hours * 60 + minutes)
+    } else {
+        None
+    }
+}
+
+/// days since the unix epoch of a date in the proleptic gregorian calendar
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// opening and closing prices of the regular sessions of a stock
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionPrices {
+    /// trading day of the latest regular session
+    pub day: Option<i64>,
+    /// first regular price of the latest session
+    pub open: Option<Price>,
+    /// last regular price of the latest session so far
+    pub close: Option<Price>,
+    /// last regular price of the session before
+    pub previous_close: Option<Price>,
+}
+
+impl SessionPrices {
+    /// adds a price traded in the regular session of a given day
+    pub fn push(&mut self, day: i64, price: Price) {
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.previous_close = self.close.or(self.previous_close);
+            self.open = Some(price);
+        }
+        self.close = Some(price);
+    }
+
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        for price in [&mut self.open, &mut self.close, &mut self.previous_close] {
+            *price = price.map(|price| adjustment.price(price));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * MINUTE;
+
+    /// utc timestamp of a date and hour
+    fn at(year: i64, month: i64, day: i64, hour: i64) -> Timestamp {
+        (days_from_civil(year, month, day) * DAY + hour * HOUR) as Timestamp
+    }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+    }
+
+    #[test]
+    fn test_nyse_sessions() {
+        let mut calendar = TradingCalendar::nyse();
+
+        // 2026-10-19 is a monday, new york is 4 hours behind utc until november
+        assert_eq!(calendar.state_at(at(2026, 10, 19, 7)), SessionState::Closed);
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 19, 8)),
+            SessionState::PreMarket
+        );
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 19, 14)),
+            SessionState::Regular
+        );
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 19, 20)),
+            SessionState::AfterHours
+        );
+        assert_eq!(calendar.state_at(at(2026, 10, 20, 0)), SessionState::Closed);
+        // and 5 hours behind in winter, 2026-12-14 is a monday too
+        assert_eq!(calendar.state_at(at(2026, 12, 14, 8)), SessionState::Closed);
+        assert_eq!(
+            calendar.state_at(at(2026, 12, 14, 14)),
+            SessionState::PreMarket
+        );
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 18, 15)),
+            SessionState::Closed
+        );
+
+        calendar.add_holiday("2026-10-19").unwrap();
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 19, 15)),
+            SessionState::Closed
+        );
+        assert!(calendar.add_holiday("2026-13-01").is_err());
+
+        let always = TradingCalendar::always_open();
+        assert_eq!(always.state_at(at(2026, 10, 18, 0)), SessionState::Regular);
+    }
+
+    #[test]
+    fn test_daylight_saving() {
+        let calendar = TradingCalendar::nyse();
+        let minute = MINUTE as Timestamp;
+
+        // 2026-03-08 and 2026-11-01 are the second sunday in march and the first in november,
+        // 2:00 local time is 7:00 utc in winter and 6:00 utc in summer
+        assert_eq!(calendar.offset_at(at(2026, 3, 8, 7) - minute), -300);
+        assert_eq!(calendar.offset_at(at(2026, 3, 8, 7)), -240);
+        assert_eq!(calendar.offset_at(at(2026, 11, 1, 6) - minute), -240);
+        assert_eq!(calendar.offset_at(at(2026, 11, 1, 6)), -300);
+        assert_eq!(calendar.offset_at(at(2027, 1, 1, 0)), -300);
+        // 2025-03-09 was the second sunday in march
+        assert_eq!(calendar.offset_at(at(2025, 3, 9, 6)), -300);
+        assert_eq!(calendar.offset_at(at(2025, 3, 9, 7)), -240);
+
+        assert_eq!(year_of(days_from_civil(2024, 12, 31)), 2024);
+        assert_eq!(year_of(days_from_civil(2025, 1, 1)), 2025);
+        assert_eq!(year_of(-1), 1969);
+        assert_eq!(
+            TradingCalendar::always_open().offset_at(at(2026, 7, 1, 0)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_calendar() {
+        assert_eq!(TradingCalendar::parse("NYSE"), Ok(TradingCalendar::nyse()));
+
+        let calendar = TradingCalendar::parse("08:00, 09:00, 17:30, 18:00, 60").unwrap();
+        assert_eq!(calendar.regular_close, 17 * 60 + 30);
+        assert_eq!(
+            calendar.state_at(at(2026, 10, 19, 8)),
+            SessionState::Regular
+        );
+
+        assert!(TradingCalendar::parse("09:00,08:00,17:30,18:00,0").is_err());
+        assert!(TradingCalendar::parse("08:00,09:00,17:30,25:00,0").is_err());
+        assert!(TradingCalendar::parse("08:00,09:75,17:30,18:00,0").is_err());
+        assert!(TradingCalendar::parse("08:00,09:00,17:30,24:30,0").is_err());
+        assert!(TradingCalendar::parse("08:00,09:00,17:30,24:00,0").is_ok());
+        assert!(TradingCalendar::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_session_prices() {
+        let mut prices = SessionPrices::default();
+        prices.push(1, Price::new(10, 0));
+        prices.push(1, Price::new(12, 0));
+        assert_eq!(
+            (prices.open, prices.previous_close),
+            (Some(Price::new(10, 0)), None)
+        );
+
+        prices.push(2, Price::new(11, 0));
+        assert_eq!(prices.open, Some(Price::new(11, 0)));
+        assert_eq!(prices.previous_close, Some(Price::new(12, 0)));
+    }
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use crate::{Adjustment, Price, Tick, Timestamp};
+
+/// how many candles are kept per stock and interval
+const MAX_CANDLES: usize = 1000;
+
+/// timeframe of a candle
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Interval {
+    #[serde(rename = "1s")]
+    OneSecond,
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 6] = [
+        Interval::OneSecond,
+        Interval::OneMinute,
+        Interval::FiveMinutes,
+        Interval::FifteenMinutes,
+        Interval::OneHour,
+        Interval::OneDay,
+    ];
+
+    /// length of the interval in milliseconds
+    pub fn millis(&self) -> Timestamp {
+        let seconds = match self {
+            Interval::OneSecond => 1,
+            Interval::OneMinute => 60,
+            Interval::FiveMinutes => 5 * 60,
+            Interval::FifteenMinutes => 15 * 60,
+            Interval::OneHour => 60 * 60,
+            Interval::OneDay => 24 * 60 * 60,
+        };
+        seconds * 1000
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::OneSecond => "1s",
+            Interval::OneMinute => "1m",
+            Interval::FiveMinutes => "5m",
+            Interval::FifteenMinutes => "15m",
+            Interval::OneHour => "1h",
+            Interval::OneDay => "1d",
+        }
+    }
+
+    /// start of the bar the given timestamp falls into
+    pub(crate) fn bucket(&self, timestamp: Timestamp) -> Timestamp {
+        timestamp - timestamp % self.millis()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::ALL
+            .iter()
+            .find(|interval| interval.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown interval {:?}", s))
+    }
+}
+
+/// open/high/low/close/volume bar
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub start: Timestamp,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: u64,
+}
+
+impl Candle {
+    fn new(start: Timestamp, tick: Tick) -> Self {
+        Self {
+            start,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.volume,
+        }
+    }
+
+    fn add(&mut self, tick: Tick) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.volume += tick.volume;
+    }
+
+    /// same candle with the prices multiplied and the volume scaled by the given factors
+    pub fn scaled(&self, price_factor: f64, volume_factor: f64) -> Self {
+        Self {
+            start: self.start,
+            open: self.open.scaled(price_factor),
+            high: self.high.scaled(price_factor),
+            low: self.low.scaled(price_factor),
+            close: self.close.scaled(price_factor),
+            volume: (self.volume as f64 * volume_factor).round() as u64,
+        }
+    }
+}
+
+/// Candle Aggregator
+/// builds the candles of one stock for every interval as ticks come in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandleAggregator {
+    series: Vec<(Interval, VecDeque<Candle>)>,
+    /// the same candles as they traded, never adjusted for corporate actions
+    #[serde(default = "empty_series")]
+    traded: Vec<(Interval, VecDeque<Candle>)>,
+}
+
+fn empty_series() -> Vec<(Interval, VecDeque<Candle>)> {
+    Interval::ALL
+        .iter()
+        .map(|interval| (*interval, VecDeque::new()))
+        .collect()
+}
+
+impl Default for CandleAggregator {
+    fn default() -> Self {
+        Self {
+            series: empty_series(),
+            traded: empty_series(),
+        }
+    }
+}
+
+impl CandleAggregator {
+    /// adds a tick to the current candle of every interval, opening new candles as needed
+    pub fn push(&mut self, tick: Tick) {
+        self.record(tick, tick);
+    }
+
+    /// adds a tick adjusted for corporate actions along with the tick as it traded
+    pub(crate) fn record(&mut self, tick: Tick, traded: Tick) {
+        for (series, tick) in [(&mut self.series, tick), (&mut self.traded, traded)] {
+            for (interval, candles) in series {
+                let start = interval.bucket(tick.timestamp);
+
+                match candles.back_mut() {
+                    Some(candle) if candle.start == start => candle.add(tick),
+                    _ => {
+                        candles.push_back(Candle::new(start, tick));
+                        if candles.len() > MAX_CANDLES {
+                            candles.pop_front();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// applies a corporate action to every candle so far, so charts stay continuous across it
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        for (_, candles) in &mut self.series {
+            for candle in candles.iter_mut() {
+                *candle = candle.scaled(adjustment.price_factor, adjustment.volume_factor);
+            }
+        }
+    }
+
+    /// latest `limit` candles of an interval, oldest first, the last one may still be open
+    pub fn candles(&self, interval: Interval, limit: usize) -> Vec<Candle> {
+        latest(&self.series, interval, limit)
+    }
+
+    /// latest `limit` candles of an interval as they traded, oldest first
+    pub fn traded_candles(&self, interval: Interval, limit: usize) -> Vec<Candle> {
+        latest(&self.traded, interval, limit)
+    }
+}
+
+fn latest(
+    series: &[(Interval, VecDeque<Candle>)],
+    interval: Interval,
+    limit: usize,
+) -> Vec<Candle> {
+    series
+        .iter()
+        .find(|(i, _)| *i == interval)
+        .map(|(_, candles)| {
+            let skip = candles.len().saturating_sub(limit);
+            candles.iter().skip(skip).copied().collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_parsing() {
+        assert_eq!("1s".parse(), Ok(Interval::OneSecond));
+        assert_eq!("15m".parse(), Ok(Interval::FifteenMinutes));
+        assert_eq!("1d".parse(), Ok(Interval::OneDay));
+        assert!("2m".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_candle_aggregator() {
+        let mut aggregator = CandleAggregator::default();
+
+        // two and a half minutes of one tick per second, price going 0, 1, 2, ...
+        for second in 0..150 {
+            aggregator.push(Tick {
+                seq: second + 1,
+                timestamp: second * 1000,
+                price: Price::new(second as i64, 0),
+                volume: 1,
+            });
+        }
+
+        let seconds = aggregator.candles(Interval::OneSecond, 200);
+        assert_eq!(seconds.len(), 150);
+        assert_eq!(seconds[10].open, Price::new(10, 0));
+        assert_eq!(seconds[10].volume, 1);
+
+        let minutes = aggregator.candles(Interval::OneMinute, 200);
+        assert_eq!(minutes.len(), 3);
+        assert_eq!(
+            minutes[1],
+            Candle {
+                start: 60_000,
+                open: Price::new(60, 0),
+                high: Price::new(119, 0),
+                low: Price::new(60, 0),
+                close: Price::new(119, 0),
+                volume: 60,
+            }
+        );
+        assert_eq!(minutes[2].volume, 30);
+
+        let last_minute = aggregator.candles(Interval::OneMinute, 1);
+        assert_eq!(last_minute.len(), 1);
+        assert_eq!(last_minute[0].start, 120_000);
+
+        let days = aggregator.candles(Interval::OneDay, 200);
+        assert_eq!(days.len(), 1);
+        assert_eq!(
+            (days[0].low, days[0].high),
+            (Price::ZERO, Price::new(149, 0))
+        );
+        assert_eq!(days[0].volume, 150);
+    }
+}
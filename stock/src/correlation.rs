@@ -0,0 +1,212 @@
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+
+use crate::Symbol;
+
+/// Correlation Matrix
+/// pairwise correlations of the returns of a set of stocks,
+/// built up from a market factor, sector factors and explicit pairs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMatrix {
+    symbols: Vec<Symbol>,
+    values: Vec<Vec<f64>>,
+}
+
+impl CorrelationMatrix {
+    /// every stock moves independently
+    pub fn identity(symbols: &[Symbol]) -> Self {
+        let n = symbols.len();
+        Self {
+            symbols: symbols.to_vec(),
+            values: (0..n)
+                .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+                .collect(),
+        }
+    }
+
+    /// builds a matrix from a spec like `market:0.5;sector:0.4:GOOG,APPL,MSFT;pair:GOOG:MSFT:0.8`,
+    /// entries apply in order so later pairs override what the factors produced
+    pub fn parse(symbols: &[Symbol], spec: &str) -> Result<Self, String> {
+        let mut matrix = Self::identity(symbols);
+        let invalid = || format!("invalid correlation {:?}", spec);
+
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+            let number = |part: &str| part.parse::<f64>().map_err(|_| invalid());
+
+            match parts.as_slice() {
+                ["market", loading] => matrix.add_market_factor(number(loading)?),
+                ["sector", loading, members] => {
+                    let members: Vec<&str> = members.split(',').map(str::trim).collect();
+                    matrix.add_sector(&members, number(loading)?)
+                }
+                ["pair", a, b, rho] => matrix.set(a, b, number(rho)?)?,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(matrix)
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        Some(self.values[self.index(a)?][self.index(b)?])
+    }
+
+    /// sets the correlation of a pair of stocks
+    pub fn set(&mut self, a: &str, b: &str, rho: f64) -> Result<(), String> {
+        let (i, j) = match (self.index(a), self.index(b)) {
+            (Some(i), Some(j)) if i != j => (i, j),
+            _ => return Err(format!("no correlation between {} and {}", a, b)),
+        };
+        if !(-1.0..=1.0).contains(&rho) {
+            return Err(format!("correlation {} is not between -1 and 1", rho));
+        }
+
+        self.values[i][j] = rho;
+        self.values[j][i] = rho;
+        Ok(())
+    }
+
+    /// every stock loads on a common market factor, adding `loading²` to every pair
+    pub fn add_market_factor(&mut self, loading: f64) {
+        let all: Vec<usize> = (0..self.symbols.len()).collect();
+        self.add_factor(&all, loading);
+    }
+
+    /// stocks of a sector load on a common sector factor, adding `loading²` to the pairs
+    /// within the sector, unknown stocks are skipped
+    pub fn add_sector(&mut self, members: &[&str], loading: f64) {
+        let members: Vec<usize> = members.iter().filter_map(|m| self.index(m)).collect();
+        self.add_factor(&members, loading);
+    }
+
+    /// lower triangular factor L with L * Lᵀ equal to the matrix,
+    /// fails if the correlations are inconsistent (the matrix is not positive definite)
+    pub fn cholesky(&self) -> Result<CorrelatedShocks, String> {
+        let n = self.symbols.len();
+        let mut factor = vec![vec![0.0; n]; n];
+
+        for i in 0..n {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+
+                if i == j {
+                    let diagonal = self.values[i][i] - sum;
+                    if diagonal <= 0.0 {
+                        return Err("correlations are not consistent with each other".into());
+                    }
+                    factor[i][j] = diagonal.sqrt();
+                } else {
+                    factor[i][j] = (self.values[i][j] - sum) / factor[j][j];
+                }
+            }
+        }
+
+        Ok(CorrelatedShocks {
+            symbols: self.symbols.clone(),
+            factor,
+        })
+    }
+
+    fn index(&self, symbol: &str) -> Option<usize> {
+        self.symbols.iter().position(|s| s == symbol)
+    }
+
+    fn add_factor(&mut self, members: &[usize], loading: f64) {
+        for &i in members {
+            for &j in members {
+                if i != j {
+                    self.values[i][j] += loading * loading;
+                }
+            }
+        }
+    }
+}
+
+/// Correlated Shocks
+/// draws one standard normal shock per stock, correlated as the matrix it was factored from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelatedShocks {
+    symbols: Vec<Symbol>,
+    factor: Vec<Vec<f64>>,
+}
+
+impl CorrelatedShocks {
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// shocks in the order of `symbols`
+    pub fn draw(&self, rng: &mut dyn RngCore) -> Vec<f64> {
+        let independent: Vec<f64> = (0..self.symbols.len())
+            .map(|_| rng.sample(StandardNormal))
+            .collect();
+
+        self.factor
+            .iter()
+            .map(|row| row.iter().zip(&independent).map(|(l, z)| l * z).sum())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn symbols() -> Vec<Symbol> {
+        vec!["GOOG".into(), "MSFT".into(), "TSLA".into()]
+    }
+
+    /// sample correlation of two columns of draws
+    fn sample_correlation(draws: &[Vec<f64>], a: usize, b: usize) -> f64 {
+        let n = draws.len() as f64;
+        let mean = |i: usize| draws.iter().map(|d| d[i]).sum::<f64>() / n;
+        let (mean_a, mean_b) = (mean(a), mean(b));
+        let covariance = |i: usize, mi: f64, j: usize, mj: f64| {
+            draws.iter().map(|d| (d[i] - mi) * (d[j] - mj)).sum::<f64>() / n
+        };
+
+        covariance(a, mean_a, b, mean_b)
+            / (covariance(a, mean_a, a, mean_a) * covariance(b, mean_b, b, mean_b)).sqrt()
+    }
+
+    #[test]
+    fn test_parse_correlation() {
+        let matrix =
+            CorrelationMatrix::parse(&symbols(), "market:0.5; sector:0.5:GOOG,MSFT,NOPE").unwrap();
+        assert_eq!(matrix.get("GOOG", "MSFT"), Some(0.5));
+        assert_eq!(matrix.get("GOOG", "TSLA"), Some(0.25));
+        assert_eq!(matrix.get("TSLA", "TSLA"), Some(1.0));
+
+        let matrix = CorrelationMatrix::parse(&symbols(), "pair:GOOG:TSLA:-0.3").unwrap();
+        assert_eq!(matrix.get("TSLA", "GOOG"), Some(-0.3));
+
+        assert!(CorrelationMatrix::parse(&symbols(), "pair:GOOG:GOOG:0.3").is_err());
+        assert!(CorrelationMatrix::parse(&symbols(), "pair:GOOG:TSLA:2").is_err());
+        assert!(CorrelationMatrix::parse(&symbols(), "market").is_err());
+    }
+
+    #[test]
+    fn test_correlated_shocks() {
+        let mut matrix = CorrelationMatrix::identity(&symbols());
+        matrix.set("GOOG", "MSFT", 0.8).unwrap();
+        matrix.set("GOOG", "TSLA", -0.5).unwrap();
+        let shocks = matrix.cholesky().unwrap();
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let draws: Vec<Vec<f64>> = (0..20_000).map(|_| shocks.draw(&mut rng)).collect();
+
+        assert!((sample_correlation(&draws, 0, 1) - 0.8).abs() < 0.03);
+        assert!((sample_correlation(&draws, 0, 2) + 0.5).abs() < 0.03);
+        assert!((sample_correlation(&draws, 1, 2) + 0.0).abs() < 0.05);
+
+        // GOOG and MSFT moving together while both move opposite TSLA can not all hold strongly
+        matrix.set("MSFT", "TSLA", 0.9).unwrap();
+        assert!(matrix.cholesky().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{Price, Symbol};
+
+/// three letter code of the currency a price is quoted in, e.g. "USD"
+pub type Currency = String;
+
+/// currency of stocks not quoted in another one, rates between other currencies cross it
+pub const BASE_CURRENCY: &str = "USD";
+/// decimal places of fx rates
+pub const FX_SCALE: u32 = 5;
+
+/// whether a code looks like a currency, three upper case letters
+pub fn is_currency(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|byte| byte.is_ascii_uppercase())
+}
+
+/// decimal places of the minor unit of a currency, 2 for cents and none for yen
+pub fn minor_units(currency: &str) -> u32 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// FX Conversion
+/// rate prices are multiplied by to quote them in another currency, along with the
+/// decimal places of that currency the converted prices have at least
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Conversion {
+    pub rate: f64,
+    pub scale: u32,
+}
+
+impl Conversion {
+    /// leaves prices as they are
+    pub const NONE: Conversion = Conversion {
+        rate: 1.0,
+        scale: 0,
+    };
+
+    /// converts into `currency` at `rate`
+    pub fn to(currency: &str, rate: f64) -> Self {
+        Self {
+            rate,
+            scale: minor_units(currency),
+        }
+    }
+
+    /// the converted price, in the finer of its own decimal places and the currency's
+    pub fn price(&self, price: Price) -> Price {
+        price.padded_to(self.scale).scaled(self.rate)
+    }
+}
+
+/// FX Pair
+/// exchange rate between two currencies ticking as an instrument of its own,
+/// the price of EURUSD is how many dollars one euro costs
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FxPair {
+    pub base: Currency,
+    pub quote: Currency,
+}
+
+impl FxPair {
+    /// symbol the pair is listed as, base and quote currency run together
+    pub fn symbol(&self) -> Symbol {
+        format!("{}{}", self.base, self.quote)
+    }
+}
+
+impl fmt::Display for FxPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+impl FromStr for FxPair {
+    type Err = String;
+
+    /// pairs like `EURUSD` or `eur/usd`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_uppercase().replace('/', "");
+        let (base, quote) = match code.get(..3).zip(code.get(3..)) {
+            Some((base, quote)) if is_currency(base) && is_currency(quote) && base != quote => {
+                (base.to_string(), quote.to_string())
+            }
+            _ => return Err(format!("invalid fx pair {:?}", s)),
+        };
+
+        Ok(Self { base, quote })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fx_pair() {
+        let pair: FxPair = "eur/usd".parse().unwrap();
+        assert_eq!((pair.base.as_str(), pair.quote.as_str()), ("EUR", "USD"));
+        assert_eq!(pair.symbol(), "EURUSD");
+        assert_eq!("EURUSD".parse(), Ok(pair));
+
+        assert!("EURUSDX".parse::<FxPair>().is_err());
+        assert!("USDUSD".parse::<FxPair>().is_err());
+        assert!("EU1USD".parse::<FxPair>().is_err());
+        assert!(is_currency("JPY") && !is_currency("jpy") && !is_currency("EURO"));
+    }
+
+    #[test]
+    fn test_conversion() {
+        // 0.0067 dollars per yen, yen prices get cents rather than rounding to whole dollars
+        let yen = Conversion::to("USD", 0.0067);
+        assert_eq!(yen.price(Price::new(1500, 0)).to_string(), "10.05");
+        assert_eq!(yen.price(Price::new(37, 0)).to_string(), "0.25");
+
+        // finer prices keep their decimal places
+        let dollars = Conversion::to("JPY", 150.0);
+        assert_eq!(dollars.price(Price::new(12_345, 3)).to_string(), "1851.750");
+        assert_eq!(dollars.price(Price::new(1, 2)).to_string(), "1.50");
+        assert_eq!(minor_units("KWD"), 3);
+        assert_eq!(Conversion::NONE.price(Price::new(7, 0)), Price::new(7, 0));
+    }
+}
@@ -0,0 +1,181 @@
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use crate::{Adjustment, SessionState, Symbol, Timestamp};
+
+/// why trading in a stock stopped
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HaltReason {
+    /// halted by an operator
+    Manual,
+    /// the market index fell through the circuit breaker
+    CircuitBreaker,
+}
+
+impl fmt::Display for HaltReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaltReason::Manual => f.write_str("manual"),
+            HaltReason::CircuitBreaker => f.write_str("circuit breaker"),
+        }
+    }
+}
+
+/// trading halt of a stock, `until` None lasts until resumed
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Halt {
+    pub reason: HaltReason,
+    pub since: Timestamp,
+    pub until: Option<Timestamp>,
+}
+
+/// something that happened to a stock or the market besides a regular tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketEvent {
+    /// price jumped from one tick to the next, `size` is the relative move
+    Jump {
+        stock: Symbol,
+        size: f64,
+    },
+    Halted {
+        stock: Symbol,
+        halt: Halt,
+    },
+    Resumed {
+        stock: Symbol,
+    },
+    /// a split or dividend took effect, the history of the stock was adjusted for it
+    CorporateAction {
+        stock: Symbol,
+        adjustment: Adjustment,
+    },
+    /// the market moved into another part of the trading day
+    SessionChanged {
+        state: SessionState,
+    },
+}
+
+/// random jumps on top of the price model, each tick of each stock jumps
+/// with `probability` by a normally distributed relative `size`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RandomJumps {
+    pub probability: f64,
+    /// standard deviation of the relative jump size, 0.05 moves about 5% either way
+    pub size: f64,
+}
+
+impl RandomJumps {
+    /// relative jump to apply to the next price, if any
+    pub fn draw(&self, rng: &mut dyn RngCore) -> Option<f64> {
+        if self.probability <= 0.0 || !rng.gen_bool(self.probability.min(1.0)) {
+            return None;
+        }
+
+        let shock: f64 = rng.sample(StandardNormal);
+        // jumps are lognormal so the price never goes negative
+        Some((self.size * shock).exp() - 1.0)
+    }
+}
+
+/// jump applied to the first tick of a stock at or after `at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledJump {
+    pub stock: Symbol,
+    pub at: Timestamp,
+    /// relative move, -0.1 drops the price by 10%
+    pub size: f64,
+}
+
+/// halts every stock when the market index falls more than `threshold`
+/// below its highest level within `window`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    /// fraction of the index, 0.07 trips on a 7% fall
+    pub threshold: f64,
+    pub window: Duration,
+    pub halt_for: Duration,
+}
+
+/// Market Index
+/// equally weighted average of the listed stocks' last prices, along with
+/// its highest level within a time window kept in a monotonic queue
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct MarketIndex {
+    /// candidates for the window's high, values strictly decreasing from front to back
+    highs: VecDeque<(Timestamp, f64)>,
+}
+
+impl MarketIndex {
+    /// records a level, returns how far it is below the high of the window as a fraction
+    pub fn push(&mut self, timestamp: Timestamp, level: f64, window: Duration) -> f64 {
+        let cutoff = timestamp.saturating_sub(window.as_millis() as Timestamp);
+        while self.highs.front().is_some_and(|(at, _)| *at < cutoff) {
+            self.highs.pop_front();
+        }
+        while self.highs.back().is_some_and(|(_, high)| *high <= level) {
+            self.highs.pop_back();
+        }
+        self.highs.push_back((timestamp, level));
+
+        match self.highs.front() {
+            Some((_, high)) if *high > 0.0 => 1.0 - level / high,
+            _ => 0.0,
+        }
+    }
+
+    /// forgets every level, so a resumed market is measured from where it restarts
+    pub fn reset(&mut self) {
+        self.highs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_random_jumps() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let never = RandomJumps {
+            probability: 0.0,
+            size: 0.1,
+        };
+        assert!((0..100).all(|_| never.draw(&mut rng).is_none()));
+
+        let always = RandomJumps {
+            probability: 1.0,
+            size: 0.1,
+        };
+        let jumps: Vec<f64> = (0..100).filter_map(|_| always.draw(&mut rng)).collect();
+        assert_eq!(jumps.len(), 100);
+        assert!(jumps.iter().all(|jump| *jump > -1.0));
+        assert!(jumps.iter().any(|jump| *jump > 0.0) && jumps.iter().any(|jump| *jump < 0.0));
+    }
+
+    #[test]
+    fn test_market_index() {
+        let window = Duration::from_secs(10);
+        let mut index = MarketIndex::default();
+
+        let mut assert_fall = |timestamp: Timestamp, level: f64, expected: f64| {
+            let fall = index.push(timestamp, level, window);
+            assert!((fall - expected).abs() < 1e-9, "{} at {}", fall, timestamp);
+        };
+
+        assert_fall(0, 100.0, 0.0);
+        assert_fall(1_000, 110.0, 0.0);
+        assert_fall(2_000, 99.0, 0.1);
+        assert_fall(5_000, 104.5, 0.05);
+
+        // the high of 110 leaves the window, 104.5 is the highest level left
+        assert_fall(12_000, 94.05, 0.1);
+
+        index.reset();
+        assert_eq!(index.push(13_000, 50.0, window), 0.0);
+    }
+}
@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{Adjustment, Price, Tick, Timestamp};
+
+/// how much raw history is kept per stock, and how evicted history is rolled up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// maximum number of raw prices kept per stock
+    pub capacity: usize,
+    /// raw prices older than this are evicted even if there is room left
+    pub max_age: Option<Duration>,
+    /// evicted prices are aggregated into roll ups spanning this much time
+    pub rollup_span: Duration,
+    /// maximum number of roll ups kept, older ones are merged into the archive
+    pub max_rollups: usize,
+}
+
+impl Default for RetentionPolicy {
+    /// one hour of raw ticks, one week of one minute roll ups
+    fn default() -> Self {
+        Self {
+            capacity: 60 * 60,
+            max_age: None,
+            rollup_span: Duration::from_secs(60),
+            max_rollups: 7 * 24 * 60,
+        }
+    }
+}
+
+/// aggregate of a span of prices that were evicted from the raw history
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollUp {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub count: u64,
+    pub sum: f64,
+    pub volume: u64,
+}
+
+impl RollUp {
+    fn new(tick: Tick) -> Self {
+        Self {
+            start: tick.timestamp,
+            end: tick.timestamp,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            count: 1,
+            sum: tick.price.to_f64(),
+            volume: tick.volume,
+        }
+    }
+
+    fn add(&mut self, tick: Tick) {
+        self.end = tick.timestamp;
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.count += 1;
+        self.sum += tick.price.to_f64();
+        self.volume += tick.volume;
+    }
+
+    /// merges a roll up that directly follows this one
+    fn merge(&mut self, next: &RollUp) {
+        self.end = next.end;
+        self.high = self.high.max(next.high);
+        self.low = self.low.min(next.low);
+        self.close = next.close;
+        self.count += next.count;
+        self.sum += next.sum;
+        self.volume += next.volume;
+    }
+
+    pub fn average(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    fn adjust(&mut self, adjustment: &Adjustment) {
+        self.open = adjustment.price(self.open);
+        self.high = adjustment.price(self.high);
+        self.low = adjustment.price(self.low);
+        self.close = adjustment.price(self.close);
+        self.sum *= adjustment.price_factor;
+        self.volume = adjustment.volume(self.volume);
+    }
+}
+
+/// Price History as kept in a snapshot, raw ticks oldest first
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct HistorySnapshot {
+    pub ticks: Vec<Tick>,
+    /// the same ticks as they traded, before any corporate action
+    #[serde(default)]
+    pub traded: Vec<Tick>,
+    pub rollups: Vec<RollUp>,
+    pub archive: Option<RollUp>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+/// Ring Buffer
+/// fixed capacity buffer that overwrites its oldest value once full,
+/// every value is written twice (at `i` and `i + capacity`) so the
+/// buffered values are always readable as one contiguous slice
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RingBuffer<T> {
+    buffer: Vec<T>,
+    capacity: usize,
+    start: usize,
+    len: usize,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buffer: vec![T::default(); capacity * 2],
+            capacity,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.buffer[self.start..self.start + self.len]
+    }
+
+    pub fn first(&self) -> Option<T> {
+        self.as_slice().first().copied()
+    }
+
+    /// appends a value, returns the evicted oldest value if the buffer was full
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let evicted = if self.len == self.capacity {
+            self.pop_front()
+        } else {
+            None
+        };
+
+        let index = (self.start + self.len) % self.capacity;
+        self.buffer[index] = value;
+        self.buffer[index + self.capacity] = value;
+        self.len += 1;
+
+        evicted
+    }
+
+    /// updates every buffered value in place
+    pub fn update(&mut self, mut f: impl FnMut(&mut T)) {
+        for offset in 0..self.len {
+            let index = (self.start + offset) % self.capacity;
+            f(&mut self.buffer[index]);
+            self.buffer[index + self.capacity] = self.buffer[index];
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let value = self.buffer[self.start];
+        self.start = (self.start + 1) % self.capacity;
+        self.len -= 1;
+
+        Some(value)
+    }
+}
+
+/// Price History
+/// bounded raw tick history of one stock,
+/// ticks falling out of the retention window are rolled up instead of dropped,
+/// prices are also buffered on their own so they can be read as a plain slice,
+/// count and sum of every price ever recorded are kept as running totals
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceHistory {
+    policy: RetentionPolicy,
+    ticks: RingBuffer<Tick>,
+    /// the retained ticks as they traded, never adjusted for corporate actions
+    traded: RingBuffer<Tick>,
+    /// prices of the raw ticks as floats, for the averages, trends and indicators
+    prices: RingBuffer<f64>,
+    rollups: VecDeque<RollUp>,
+    archive: Option<RollUp>,
+    count: u64,
+    sum: f64,
+}
+
+impl PriceHistory {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            policy,
+            ticks: RingBuffer::new(policy.capacity),
+            traded: RingBuffer::new(policy.capacity),
+            prices: RingBuffer::new(policy.capacity),
+            rollups: VecDeque::new(),
+            archive: None,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// raw ticks still in the retention window, oldest first
+    pub fn ticks(&self) -> &[Tick] {
+        self.ticks.as_slice()
+    }
+
+    /// raw ticks still in the retention window as they traded, oldest first
+    pub fn traded_ticks(&self) -> &[Tick] {
+        self.traded.as_slice()
+    }
+
+    /// prices of the raw ticks, oldest first
+    pub fn prices(&self) -> &[f64] {
+        self.prices.as_slice()
+    }
+
+    /// roll ups of evicted prices, oldest first
+    pub fn rollups(&self) -> &VecDeque<RollUp> {
+        &self.rollups
+    }
+
+    /// single roll up of everything older than the oldest roll up
+    pub fn archive(&self) -> Option<&RollUp> {
+        self.archive.as_ref()
+    }
+
+    pub fn last_tick(&self) -> Option<Tick> {
+        self.ticks().last().copied()
+    }
+
+    pub fn last_price(&self) -> Option<Price> {
+        self.last_tick().map(|tick| tick.price)
+    }
+
+    /// latest raw tick recorded at or before the given timestamp
+    pub fn tick_at(&self, timestamp: Timestamp) -> Option<Tick> {
+        let ticks = self.ticks();
+        let index = ticks.partition_point(|tick| tick.timestamp <= timestamp);
+
+        index.checked_sub(1).map(|index| ticks[index])
+    }
+
+    /// number of prices ever recorded, raw and rolled up
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// average of every price ever recorded, raw and rolled up
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    /// appends a new tick and evicts whatever falls out of the retention window
+    pub fn push(&mut self, tick: Tick) {
+        self.record(tick, tick);
+    }
+
+    /// appends a tick adjusted for corporate actions along with the tick as it traded
+    pub(crate) fn record(&mut self, tick: Tick, traded: Tick) {
+        if let Some(max_age) = self.policy.max_age {
+            let cutoff = tick
+                .timestamp
+                .saturating_sub(max_age.as_millis() as Timestamp);
+            while self
+                .ticks
+                .first()
+                .is_some_and(|first| first.timestamp < cutoff)
+            {
+                self.evict();
+            }
+        }
+
+        self.count += 1;
+        self.sum += tick.price.to_f64();
+        self.prices.push(tick.price.to_f64());
+        self.traded.push(traded);
+        if let Some(evicted) = self.ticks.push(tick) {
+            self.roll_up(evicted);
+        }
+    }
+
+    /// applies a corporate action to everything recorded so far
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        self.ticks.update(|tick| {
+            tick.price = adjustment.price(tick.price);
+            tick.volume = adjustment.volume(tick.volume);
+        });
+        let mut adjusted = self.ticks.as_slice().iter().map(|tick| tick.price.to_f64());
+        self.prices
+            .update(|price| *price = adjusted.next().unwrap_or(*price));
+        self.rollups
+            .iter_mut()
+            .for_each(|rollup| rollup.adjust(adjustment));
+        if let Some(archive) = &mut self.archive {
+            archive.adjust(adjustment);
+        }
+        self.sum *= adjustment.price_factor;
+    }
+
+    /// what a snapshot keeps of the history, everything but the retention policy
+    pub fn snapshot(&self) -> HistorySnapshot {
+        HistorySnapshot {
+            ticks: self.ticks().to_vec(),
+            traded: self.traded_ticks().to_vec(),
+            rollups: self.rollups.iter().copied().collect(),
+            archive: self.archive,
+            count: self.count,
+            sum: self.sum,
+        }
+    }
+
+    /// history restored from a snapshot under the given policy, ticks it no longer
+    /// retains are rolled up as if they had just been evicted
+    pub fn restore(policy: RetentionPolicy, snapshot: &HistorySnapshot) -> Self {
+        let mut history = Self::new(policy);
+        history.rollups = snapshot.rollups.iter().copied().collect();
+        history.archive = snapshot.archive;
+        history.trim_rollups();
+
+        // snapshots without the traded ticks only know the adjusted ones
+        let traded = if snapshot.traded.len() == snapshot.ticks.len() {
+            &snapshot.traded
+        } else {
+            &snapshot.ticks
+        };
+        for (tick, traded) in snapshot.ticks.iter().zip(traded) {
+            history.record(*tick, *traded);
+        }
+        history.count = snapshot.count;
+        history.sum = snapshot.sum;
+
+        history
+    }
+
+    fn evict(&mut self) {
+        self.prices.pop_front();
+        self.traded.pop_front();
+        if let Some(evicted) = self.ticks.pop_front() {
+            self.roll_up(evicted);
+        }
+    }
+
+    fn roll_up(&mut self, tick: Tick) {
+        let span = self.policy.rollup_span.as_millis() as Timestamp;
+
+        match self.rollups.back_mut() {
+            Some(rollup) if tick.timestamp < rollup.start + span => rollup.add(tick),
+            _ => self.rollups.push_back(RollUp::new(tick)),
+        }
+        self.trim_rollups();
+    }
+
+    /// merges the roll ups beyond max_rollups into the archive
+    fn trim_rollups(&mut self) {
+        while self.rollups.len() > self.policy.max_rollups {
+            if let Some(oldest) = self.rollups.pop_front() {
+                match &mut self.archive {
+                    Some(archive) => archive.merge(&oldest),
+                    None => self.archive = Some(oldest),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(second: u64, price: f64) -> Tick {
+        Tick {
+            seq: second + 1,
+            timestamp: second * 1000,
+            price: Price::from_f64(price, 2),
+            volume: 1,
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut buffer = RingBuffer::new(3);
+        assert!(buffer.as_slice().is_empty());
+
+        assert_eq!(buffer.push(1), None);
+        assert_eq!(buffer.push(2), None);
+        assert_eq!(buffer.push(3), None);
+        assert_eq!(buffer.as_slice(), &[1, 2, 3]);
+
+        assert_eq!(buffer.push(4), Some(1));
+        assert_eq!(buffer.push(5), Some(2));
+        assert_eq!(buffer.as_slice(), &[3, 4, 5]);
+
+        assert_eq!(buffer.pop_front(), Some(3));
+        assert_eq!(buffer.as_slice(), &[4, 5]);
+        assert_eq!(buffer.push(6), None);
+        assert_eq!(buffer.push(7), Some(4));
+        assert_eq!(buffer.as_slice(), &[5, 6, 7]);
+    }
+
+    #[test]
+    fn test_price_history_rolls_up_by_count() {
+        let mut history = PriceHistory::new(RetentionPolicy {
+            capacity: 10,
+            max_age: None,
+            rollup_span: Duration::from_secs(5),
+            max_rollups: 2,
+        });
+
+        // one price per second, 0 to 29
+        for second in 0..30 {
+            history.push(tick(second, second as f64));
+        }
+
+        assert_eq!(history.prices().len(), 10);
+        assert_eq!(history.prices().first(), Some(&20.0));
+        assert_eq!(history.ticks().first(), Some(&tick(20, 20.0)));
+        assert_eq!(history.last_tick(), Some(tick(29, 29.0)));
+        assert_eq!(history.tick_at(25_500), Some(tick(25, 25.0)));
+        assert_eq!(history.tick_at(19_000), None);
+
+        // 20 evicted prices make four 5 second roll ups, two of them merged into the archive
+        assert_eq!(history.rollups().len(), 2);
+        let rollup = history.rollups()[0];
+        assert_eq!((rollup.start, rollup.end), (10_000, 14_000));
+        let (ten, fourteen) = (Price::new(10, 0), Price::new(14, 0));
+        assert_eq!((rollup.open, rollup.close), (ten, fourteen));
+        assert_eq!((rollup.low, rollup.high), (ten, fourteen));
+        assert_eq!(rollup.count, 5);
+        assert_eq!(rollup.volume, 5);
+
+        let archive = history.archive().unwrap();
+        assert_eq!((archive.start, archive.end, archive.count), (0, 9_000, 10));
+        assert_eq!(archive.average(), 4.5);
+
+        // nothing was dropped
+        assert_eq!(history.count(), 30);
+        assert_eq!(history.average(), 14.5);
+    }
+
+    #[test]
+    fn test_price_history_evicts_by_age() {
+        let mut history = PriceHistory::new(RetentionPolicy {
+            capacity: 100,
+            max_age: Some(Duration::from_secs(10)),
+            ..RetentionPolicy::default()
+        });
+
+        for second in 0..30 {
+            history.push(tick(second, 1.0));
+        }
+
+        assert_eq!(history.prices().len(), 11);
+        assert_eq!(history.ticks().first().unwrap().timestamp, 19_000);
+        assert_eq!(history.count(), 30);
+    }
+
+    #[test]
+    fn test_price_history_snapshot() {
+        let policy = RetentionPolicy {
+            capacity: 10,
+            max_age: None,
+            rollup_span: Duration::from_secs(5),
+            max_rollups: 2,
+        };
+        let mut history = PriceHistory::new(policy);
+        for second in 0..30 {
+            history.push(tick(second, second as f64));
+        }
+
+        let snapshot = history.snapshot();
+        assert_eq!(PriceHistory::restore(policy, &snapshot), history);
+
+        // a smaller window rolls up the ticks it no longer retains
+        let smaller = PriceHistory::restore(
+            RetentionPolicy {
+                capacity: 5,
+                max_rollups: 1,
+                ..policy
+            },
+            &snapshot,
+        );
+        assert_eq!(smaller.ticks().first(), Some(&tick(25, 25.0)));
+        assert_eq!(smaller.rollups().len(), 1);
+        assert_eq!(smaller.archive().unwrap().count, 20);
+        assert_eq!((smaller.count(), smaller.average()), (30, 14.5));
+    }
+}
@@ -0,0 +1,308 @@
+use std::{convert::TryFrom, fmt, io::BufRead};
+
+use crate::{calendar::days_from_civil, Price, RegistryError, Symbol, Timestamp};
+
+/// what an import does to the stocks that are already listed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImportMode {
+    /// listed stocks keep ticking from where their history ends,
+    /// symbols the file adds are listed next to them
+    Seed,
+    /// only the symbols of the file stay listed
+    Replace,
+}
+
+impl std::str::FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "seed" => Ok(ImportMode::Seed),
+            "replace" => Ok(ImportMode::Replace),
+            _ => Err(format!("unknown import mode {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ImportError {
+    Io(String),
+    /// a row that does not parse, lines count from 1
+    InvalidRow {
+        line: usize,
+        reason: String,
+    },
+    Registry(RegistryError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(err) => write!(f, "could not read prices: {}", err),
+            ImportError::InvalidRow { line, reason } => write!(f, "line {}: {}", line, reason),
+            ImportError::Registry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<RegistryError> for ImportError {
+    fn from(err: RegistryError) -> Self {
+        ImportError::Registry(err)
+    }
+}
+
+/// one imported price, OHLC rows turn into four of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPrice {
+    pub stock: Symbol,
+    pub timestamp: Timestamp,
+    pub price: Price,
+    pub volume: u64,
+}
+
+/// where the fields of a row are
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Columns {
+    symbol: usize,
+    timestamp: usize,
+    price: Option<usize>,
+    /// open, high, low and close
+    ohlc: Option<[usize; 4]>,
+    volume: Option<usize>,
+    len: usize,
+}
+
+impl Columns {
+    /// `symbol,timestamp,price[,volume]` or `symbol,timestamp,open,high,low,close[,volume]`
+    fn by_count(len: usize) -> Option<Self> {
+        let (price, ohlc, volume) = match len {
+            3 => (Some(2), None, None),
+            4 => (Some(2), None, Some(3)),
+            6 => (None, Some([2, 3, 4, 5]), None),
+            7 => (None, Some([2, 3, 4, 5]), Some(6)),
+            _ => return None,
+        };
+        Some(Self {
+            symbol: 0,
+            timestamp: 1,
+            price,
+            ohlc,
+            volume,
+            len,
+        })
+    }
+
+    /// columns named by a header row, in any order
+    fn by_header(fields: &[&str]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            fields
+                .iter()
+                .position(|field| names.contains(&field.to_lowercase().as_str()))
+        };
+        let ohlc = match (
+            find(&["open"]),
+            find(&["high"]),
+            find(&["low"]),
+            find(&["close"]),
+        ) {
+            (Some(open), Some(high), Some(low), Some(close)) => Some([open, high, low, close]),
+            _ => None,
+        };
+
+        Some(Self {
+            symbol: find(&["symbol", "stock", "ticker"])?,
+            timestamp: find(&["timestamp", "time", "date", "ts"])?,
+            price: find(&["price"]).or_else(|| ohlc.map(|ohlc| ohlc[3])),
+            ohlc,
+            volume: find(&["volume", "size", "quantity"]),
+            len: fields.len(),
+        })
+    }
+}
+
+/// reads prices from csv rows of `symbol,timestamp,price[,volume]` or
+/// `symbol,timestamp,open,high,low,close[,volume]`, with or without a header naming them,
+/// timestamps are milliseconds since the unix epoch or utc dates like `2021-01-04`
+/// and `2021-01-04T15:30:00Z`, prices come back in the order they were read
+pub fn parse_csv<R: BufRead>(reader: R) -> Result<Vec<ImportedPrice>, ImportError> {
+    let mut columns = None;
+    let mut prices = vec![];
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| ImportError::Io(err.to_string()))?;
+        let number = index + 1;
+        let invalid = |reason: String| ImportError::InvalidRow {
+            line: number,
+            reason,
+        };
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let layout = match columns {
+            Some(columns) => columns,
+            None => {
+                let is_header = fields
+                    .get(1)
+                    .is_some_and(|field| parse_timestamp(field).is_none());
+                let found = if is_header {
+                    Columns::by_header(&fields)
+                } else {
+                    Columns::by_count(fields.len())
+                };
+                let found = found.ok_or_else(|| invalid("unknown columns".into()))?;
+                columns = Some(found);
+                if is_header {
+                    continue;
+                }
+                found
+            }
+        };
+        if fields.len() != layout.len {
+            return Err(invalid(format!("expected {} fields", layout.len)));
+        }
+
+        let stock = fields[layout.symbol].to_uppercase();
+        let timestamp = parse_timestamp(fields[layout.timestamp])
+            .ok_or_else(|| invalid(format!("invalid timestamp {:?}", fields[layout.timestamp])))?;
+        let invalid_number = |index: usize| invalid(format!("invalid number {:?}", fields[index]));
+        // prices are read as exact decimals, volumes may come as floats like `100.0`
+        let number = |index: usize| {
+            fields[index]
+                .parse::<Price>()
+                .ok()
+                .filter(|price| *price >= Price::ZERO)
+                .ok_or_else(|| invalid_number(index))
+        };
+        let volume = match layout.volume {
+            Some(index) => fields[index]
+                .parse::<f64>()
+                .ok()
+                .filter(|volume| volume.is_finite() && *volume >= 0.0)
+                .ok_or_else(|| invalid_number(index))? as u64,
+            None => 0,
+        };
+
+        let row: Vec<Price> = match (layout.ohlc, layout.price) {
+            (Some([open, high, low, close]), _) => {
+                let (open, high, low, close) =
+                    (number(open)?, number(high)?, number(low)?, number(close)?);
+                // a falling bar most likely made its high first
+                if close < open {
+                    vec![open, high, low, close]
+                } else {
+                    vec![open, low, high, close]
+                }
+            }
+            (None, Some(price)) => vec![number(price)?],
+            (None, None) => return Err(invalid("no price column".into())),
+        };
+
+        let count = row.len() as u64;
+        for (i, price) in row.into_iter().enumerate() {
+            // the volume of a bar is spread over its prices, the close takes the remainder
+            let share = if i as u64 == count - 1 {
+                volume - volume / count * (count - 1)
+            } else {
+                volume / count
+            };
+            prices.push(ImportedPrice {
+                stock: stock.clone(),
+                timestamp,
+                price,
+                volume: share,
+            });
+        }
+    }
+
+    Ok(prices)
+}
+
+/// milliseconds since the unix epoch, `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SS[Z]` in utc
+fn parse_timestamp(field: &str) -> Option<Timestamp> {
+    if !field.is_empty() && field.bytes().all(|byte| byte.is_ascii_digit()) {
+        return field.parse().ok();
+    }
+
+    let field = field.trim_end_matches('Z');
+    let (date, time) = match field.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (field, None),
+    };
+    let numbers = |text: &str, separator: char| {
+        text.split(separator)
+            .map(|part| part.parse::<i64>().ok())
+            .collect::<Option<Vec<i64>>>()
+    };
+
+    let day = match numbers(date, '-')?.as_slice() {
+        [year, month, day] if (1..=12).contains(month) && (1..=31).contains(day) => {
+            days_from_civil(*year, *month, *day)
+        }
+        _ => return None,
+    };
+    let seconds = match time.map(|time| numbers(time, ':')) {
+        None => 0,
+        Some(Some(parts)) => match parts.as_slice() {
+            [hours, minutes] => hours * 3600 + minutes * 60,
+            [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+            _ => return None,
+        },
+        Some(None) => return None,
+    };
+
+    let millis = (day * 86_400 + seconds) * 1000;
+    Timestamp::try_from(millis).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1634567890123"), Some(1_634_567_890_123));
+        assert_eq!(parse_timestamp("1970-01-02"), Some(86_400_000));
+        assert_eq!(parse_timestamp("1970-01-01T01:00:30Z"), Some(3_630_000));
+        assert_eq!(parse_timestamp("1969-12-31"), None);
+        assert_eq!(parse_timestamp("price"), None);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let ticks = "APPL,1000,10.5,100\nappl,2000,11\n";
+        assert!(parse_csv(ticks.as_bytes()).is_err());
+
+        let ticks = "# prices\nAPPL,1000,10.5,100\n\nappl,2000,11,0\n";
+        let prices = parse_csv(ticks.as_bytes()).unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[1].stock, "APPL");
+        assert_eq!(
+            (prices[0].price.to_string(), prices[0].volume),
+            ("10.5".into(), 100)
+        );
+
+        let bars = "date,ticker,close,volume,open,high,low\n1970-01-02,GOOG,9,10,10,12,8\n";
+        let prices = parse_csv(bars.as_bytes()).unwrap();
+        let series: Vec<Price> = prices.iter().map(|price| price.price).collect();
+        let expected: Vec<Price> = [10, 12, 8, 9]
+            .iter()
+            .map(|units| Price::new(*units, 0))
+            .collect();
+        assert_eq!(series, expected);
+        assert_eq!(prices.iter().map(|price| price.volume).sum::<u64>(), 10);
+        assert!(prices.iter().all(|price| price.timestamp == 86_400_000));
+
+        let err = parse_csv("GOOG,1000,-1\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            err,
+            ImportError::InvalidRow {
+                line: 1,
+                reason: "invalid number \"-1\"".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::utils::{moving_average, simple_moving_average};
+
+const MACD_FAST: usize = 12;
+const MACD_SLOW: usize = 26;
+const MACD_SIGNAL: usize = 9;
+const BOLLINGER_DEVIATIONS: f64 = 2.0;
+const STOCHASTIC_SMOOTHING: usize = 3;
+
+/// technical indicators computed over the retained price history of a stock
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Indicator {
+    /// relative strength index with Wilder's smoothing
+    Rsi,
+    /// 12/26 ema difference with a 9 ema signal line, `period` is not used
+    Macd,
+    /// simple average with bands two standard deviations away
+    Bollinger,
+    /// average true range, ticks have no range so it is taken between consecutive prices
+    Atr,
+    /// stochastic oscillator %K with a 3 period %D
+    Stochastic,
+    /// rate of change in percent
+    Roc,
+}
+
+impl Indicator {
+    pub const ALL: [Indicator; 6] = [
+        Indicator::Rsi,
+        Indicator::Macd,
+        Indicator::Bollinger,
+        Indicator::Atr,
+        Indicator::Stochastic,
+        Indicator::Roc,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Indicator::Rsi => "rsi",
+            Indicator::Macd => "macd",
+            Indicator::Bollinger => "bollinger",
+            Indicator::Atr => "atr",
+            Indicator::Stochastic => "stochastic",
+            Indicator::Roc => "roc",
+        }
+    }
+
+    /// period used when none is given
+    pub fn default_period(&self) -> usize {
+        match self {
+            Indicator::Rsi | Indicator::Atr | Indicator::Stochastic => 14,
+            Indicator::Macd => MACD_SLOW,
+            Indicator::Bollinger => 20,
+            Indicator::Roc => 10,
+        }
+    }
+
+    /// computes the indicator over the given prices, None until there is enough data
+    pub fn compute(&self, prices: &[f64], period: usize) -> Option<IndicatorValue> {
+        if period == 0 {
+            return None;
+        }
+
+        match self {
+            Indicator::Rsi => rsi(prices, period).map(IndicatorValue::Rsi),
+            Indicator::Macd => macd(prices),
+            Indicator::Bollinger => bollinger(prices, period),
+            Indicator::Atr => atr(prices, period).map(IndicatorValue::Atr),
+            Indicator::Stochastic => stochastic(prices, period),
+            Indicator::Roc => rate_of_change(prices, period).map(IndicatorValue::Roc),
+        }
+    }
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Indicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Indicator::ALL
+            .iter()
+            .find(|indicator| indicator.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown indicator {:?}", s))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorValue {
+    Rsi(f64),
+    Macd {
+        macd: f64,
+        signal: f64,
+        histogram: f64,
+    },
+    Bollinger {
+        lower: f64,
+        middle: f64,
+        upper: f64,
+    },
+    Atr(f64),
+    Stochastic {
+        k: f64,
+        d: f64,
+    },
+    Roc(f64),
+}
+
+/// exponential averages of every window ending at `window - 1` and later,
+/// seeded with the simple average of the first window
+fn ema_series(prices: &[f64], window: usize) -> Vec<f64> {
+    let seed = match simple_moving_average(&prices[..window.min(prices.len())], window) {
+        Some(seed) => seed,
+        None => return vec![],
+    };
+    let alpha = 2.0 / (window as f64 + 1.0);
+
+    let mut series = Vec::with_capacity(prices.len() - window + 1);
+    series.push(seed);
+    for price in &prices[window..] {
+        let last = series[series.len() - 1];
+        series.push(alpha * price + (1.0 - alpha) * last);
+    }
+    series
+}
+
+/// Wilder smoothed average of the given values, seeded with the simple average of the first `period`
+pub(crate) fn wilder_average(values: &[f64], period: usize) -> Option<f64> {
+    let seed = simple_moving_average(&values[..period.min(values.len())], period)?;
+
+    Some(values[period..].iter().fold(seed, |average, value| {
+        (average * (period - 1) as f64 + value) / period as f64
+    }))
+}
+
+fn rsi(prices: &[f64], period: usize) -> Option<f64> {
+    let changes: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    let gains: Vec<f64> = changes.iter().map(|change| change.max(0.0)).collect();
+    let losses: Vec<f64> = changes.iter().map(|change| (-change).max(0.0)).collect();
+
+    let average_gain = wilder_average(&gains, period)?;
+    let average_loss = wilder_average(&losses, period)?;
+
+    if average_loss == 0.0 {
+        Some(100.0)
+    } else {
+        Some(100.0 - 100.0 / (1.0 + average_gain / average_loss))
+    }
+}
+
+fn macd(prices: &[f64]) -> Option<IndicatorValue> {
+    let fast = ema_series(prices, MACD_FAST);
+    let slow = ema_series(prices, MACD_SLOW);
+    if slow.is_empty() {
+        return None;
+    }
+
+    // both series end at the latest price, line them up from the start of the slow one
+    let offset = fast.len() - slow.len();
+    let macd_line: Vec<f64> = slow
+        .iter()
+        .enumerate()
+        .map(|(i, slow)| fast[i + offset] - slow)
+        .collect();
+
+    let signal = *ema_series(&macd_line, MACD_SIGNAL).last()?;
+    let macd = *macd_line.last()?;
+
+    Some(IndicatorValue::Macd {
+        macd,
+        signal,
+        histogram: macd - signal,
+    })
+}
+
+fn bollinger(prices: &[f64], period: usize) -> Option<IndicatorValue> {
+    let middle = simple_moving_average(prices, period)?;
+    let window = &prices[prices.len() - period..];
+    let variance = moving_average(
+        &window
+            .iter()
+            .map(|price| (price - middle).powi(2))
+            .collect::<Vec<f64>>(),
+    );
+    let deviation = variance.sqrt() * BOLLINGER_DEVIATIONS;
+
+    Some(IndicatorValue::Bollinger {
+        lower: middle - deviation,
+        middle,
+        upper: middle + deviation,
+    })
+}
+
+fn atr(prices: &[f64], period: usize) -> Option<f64> {
+    let true_ranges: Vec<f64> = prices.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+
+    wilder_average(&true_ranges, period)
+}
+
+/// %K of the window ending at `end` (exclusive)
+fn stochastic_k(prices: &[f64], end: usize, period: usize) -> f64 {
+    let window = &prices[end - period..end];
+    let lowest = window.iter().copied().fold(f64::INFINITY, f64::min);
+    let highest = window.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if highest == lowest {
+        50.0
+    } else {
+        (prices[end - 1] - lowest) / (highest - lowest) * 100.0
+    }
+}
+
+fn stochastic(prices: &[f64], period: usize) -> Option<IndicatorValue> {
+    if prices.len() < period.checked_add(STOCHASTIC_SMOOTHING - 1)? {
+        return None;
+    }
+
+    let ks: Vec<f64> = (prices.len() + 1 - STOCHASTIC_SMOOTHING..=prices.len())
+        .map(|end| stochastic_k(prices, end, period))
+        .collect();
+
+    Some(IndicatorValue::Stochastic {
+        k: *ks.last()?,
+        d: moving_average(&ks),
+    })
+}
+
+fn rate_of_change(prices: &[f64], period: usize) -> Option<f64> {
+    let current = *prices.last()?;
+    let past = prices[prices.len().checked_sub(1)?.checked_sub(period)?];
+
+    if past == 0.0 {
+        None
+    } else {
+        Some((current - past) / past * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_indicator_parsing() {
+        assert_eq!("rsi".parse(), Ok(Indicator::Rsi));
+        assert_eq!("MACD".parse(), Ok(Indicator::Macd));
+        assert!("adx".parse::<Indicator>().is_err());
+    }
+
+    #[test]
+    fn test_rsi() {
+        let rising: Vec<f64> = (0..20).map(|v| v as f64).collect();
+        assert_eq!(
+            Indicator::Rsi.compute(&rising, 14),
+            Some(IndicatorValue::Rsi(100.0))
+        );
+        assert_eq!(Indicator::Rsi.compute(&rising[..14], 14), None);
+
+        // alternating +2 / -1 moves, gains average 1 and losses 0.5 over 4 changes
+        let prices = vec![10., 12., 11., 13., 12.];
+        match Indicator::Rsi.compute(&prices, 4) {
+            Some(IndicatorValue::Rsi(rsi)) => assert_close(rsi, 100.0 - 100.0 / 3.0),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_macd() {
+        let flat = vec![5.0; 40];
+        assert_eq!(
+            Indicator::Macd.compute(&flat, 0),
+            None,
+            "a period of 0 is never valid"
+        );
+        assert_eq!(
+            Indicator::Macd.compute(&flat, 26),
+            Some(IndicatorValue::Macd {
+                macd: 0.0,
+                signal: 0.0,
+                histogram: 0.0,
+            })
+        );
+        assert_eq!(Indicator::Macd.compute(&flat[..30], 26), None);
+
+        let rising: Vec<f64> = (0..60).map(|v| v as f64).collect();
+        match Indicator::Macd.compute(&rising, 26) {
+            Some(IndicatorValue::Macd {
+                macd,
+                signal,
+                histogram,
+            }) => {
+                // on a straight line the fast ema leads the slow one by (26 - 12) / 2
+                assert_close(macd, 7.0);
+                assert_close(signal, 7.0);
+                assert_close(histogram, 0.0);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bollinger_atr_stochastic_roc() {
+        let prices = vec![2., 4., 4., 4., 5., 5., 7., 9.];
+
+        // mean 5 and population deviation 2
+        assert_eq!(
+            Indicator::Bollinger.compute(&prices, 8),
+            Some(IndicatorValue::Bollinger {
+                lower: 1.0,
+                middle: 5.0,
+                upper: 9.0,
+            })
+        );
+
+        // true ranges 2, 0, 0, 1, 0, 2, 2, seeded with (2 + 0 + 0) / 3
+        match Indicator::Atr.compute(&prices, 3) {
+            Some(IndicatorValue::Atr(atr)) => {
+                let expected = [1.0, 0.0, 2.0, 2.0]
+                    .iter()
+                    .fold(2.0 / 3.0, |avg, tr| (avg * 2.0 + tr) / 3.0);
+                assert_close(atr, expected);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // last three %K over 3 prices: (5-4)/(5-4), (7-5)/(7-5), (9-5)/(9-5)
+        assert_eq!(
+            Indicator::Stochastic.compute(&prices, 3),
+            Some(IndicatorValue::Stochastic { k: 100.0, d: 100.0 })
+        );
+
+        assert_eq!(
+            Indicator::Roc.compute(&prices, 7),
+            Some(IndicatorValue::Roc(350.0))
+        );
+        assert_eq!(Indicator::Roc.compute(&prices, 8), None);
+
+        for indicator in Indicator::ALL {
+            assert_eq!(
+                indicator.compute(&prices, usize::MAX),
+                None,
+                "{}",
+                indicator
+            );
+        }
+    }
+}
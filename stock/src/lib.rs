@@ -1,11 +1,83 @@
-use rand::{self, prelude::ThreadRng, Rng};
-use std::collections::HashMap;
-use utils::{get_trend, moving_average};
+use rand::{self, Rng, RngCore};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use utils::now;
+mod actions;
+mod averages;
+mod calendar;
+mod candles;
+mod correlation;
+mod currency;
+mod events;
+mod history;
+mod import;
+mod indicators;
+mod order_book;
+mod price;
+mod price_model;
+mod quotes;
+mod registry;
+mod rolling;
+mod snapshot;
+mod spec;
+mod trend;
 mod utils;
+mod volume;
+pub use actions::{Adjustment, CorporateAction, ScheduledAction};
+use averages::AverageTracker;
+pub use averages::{AverageKind, MovingAverage};
+pub use calendar::{SessionPrices, SessionState, TradingCalendar};
+pub use candles::{Candle, CandleAggregator, Interval};
+pub use correlation::{CorrelatedShocks, CorrelationMatrix};
+pub use currency::{
+    is_currency, minor_units, Conversion, Currency, FxPair, BASE_CURRENCY, FX_SCALE,
+};
+use events::MarketIndex;
+pub use events::{CircuitBreaker, Halt, HaltReason, MarketEvent, RandomJumps, ScheduledJump};
+pub use history::{HistorySnapshot, PriceHistory, RetentionPolicy, RollUp};
+pub use import::{parse_csv, ImportError, ImportMode, ImportedPrice};
+pub use indicators::{Indicator, IndicatorValue};
+pub use order_book::{
+    Depth, DepthLevel, Execution, NewOrder, Order, OrderBook, OrderError, OrderId, OrderStatus,
+    OrderType, Side, Trade,
+};
+pub use price::{Price, DEFAULT_SCALE, MAX_SCALE};
+pub use price_model::{GeometricBrownianMotion, PriceModel, UniformModel};
+pub use quotes::{parse_spread_model, FixedSpread, Quote, RelativeSpread, SpreadModel};
+pub use registry::{RegistryError, Symbol, SymbolRegistry};
 use serde::{Deserialize, Serialize};
+pub use snapshot::{JournalEntry, Snapshot, SnapshotError, StockSnapshot, SNAPSHOT_VERSION};
+pub use trend::{
+    parse_trend_detector, AverageDirectionalIndex, LinearRegression, MovingAverageCrossover,
+    QuarterComparison, TrendDetector, TrendReading,
+};
+pub use volume::{LogNormalVolume, VolumeModel, VolumeStats};
 
-const STOCKS: [&'static str; 6] = ["GOOG", "APPL", "TSLA", "AMZN", "MSFT", "FB"];
-pub(crate) type Price = f64;
+const STOCKS: [&str; 6] = ["GOOG", "APPL", "TSLA", "AMZN", "MSFT", "FB"];
+/// milliseconds since the unix epoch
+pub type Timestamp = u64;
+
+/// single recorded price of a stock
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub struct Tick {
+    /// per stock sequence number, the first tick of a stock is 1
+    pub seq: u64,
+    pub timestamp: Timestamp,
+    pub price: Price,
+    pub volume: u64,
+}
+
+impl Tick {
+    /// same tick with its price converted to another currency
+    pub fn converted(&self, conversion: Conversion) -> Self {
+        Self {
+            price: conversion.price(self.price),
+            ..*self
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone)]
 pub enum StockTrend {
@@ -15,99 +87,1249 @@ pub enum StockTrend {
     NotEnoughData,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct StockSummary {
-    pub trend: StockTrend,
+    pub trend: TrendReading,
     pub lowest_price: Option<Price>,
     pub highest_price: Option<Price>,
+    /// average of every price ever recorded
     pub moving_average: Price,
+    /// windowed averages keyed by their name, e.g. "SMA(20)", None until the window is filled
+    pub averages: BTreeMap<String, Option<Price>>,
+    /// shares traded since the stock was listed
+    pub volume: u64,
+    /// shares traded in the current session (utc day)
+    pub session_volume: u64,
+    /// volume weighted average price of the current session
+    pub vwap: Option<Price>,
+    /// last regular price of the session before the latest one
+    pub previous_close: Option<Price>,
+    /// last price less the opening price of the latest regular session
+    pub change_since_open: Option<Price>,
+}
+
+impl StockSummary {
+    /// same summary with every price converted to another currency
+    pub fn converted(&self, conversion: Conversion) -> Self {
+        let convert = |price: Price| conversion.price(price);
+        Self {
+            lowest_price: self.lowest_price.map(convert),
+            highest_price: self.highest_price.map(convert),
+            moving_average: convert(self.moving_average),
+            averages: self
+                .averages
+                .iter()
+                .map(|(name, value)| (name.clone(), value.map(convert)))
+                .collect(),
+            vwap: self.vwap.map(convert),
+            previous_close: self.previous_close.map(convert),
+            change_since_open: self.change_since_open.map(convert),
+            ..self.clone()
+        }
+    }
 }
 
 /// Holds our stock data
+/// summaries are kept up to date from running aggregates,
+/// so a tick costs the same however much history was recorded
 #[derive(Debug)]
 pub struct StockData {
-    lowest: HashMap<&'static str, Option<Price>>,
-    highest: HashMap<&'static str, Option<Price>>,
-    data: HashMap<&'static str, Vec<Price>>,
-    summaries: HashMap<&'static str, Option<StockSummary>>,
+    registry: SymbolRegistry,
+    retention: RetentionPolicy,
+    averages: Vec<MovingAverage>,
+    trend_detector: Box<dyn TrendDetector>,
+    spread_model: Box<dyn SpreadModel>,
+    correlation: Option<CorrelatedShocks>,
+    random_jumps: Option<RandomJumps>,
+    scheduled_jumps: Vec<ScheduledJump>,
+    circuit_breaker: Option<CircuitBreaker>,
+    market_index: MarketIndex,
+    halts: HashMap<Symbol, Halt>,
+    scheduled_actions: Vec<ScheduledAction>,
+    /// corporate actions that took effect, oldest first
+    adjustments: HashMap<Symbol, Vec<Adjustment>>,
+    calendar: TradingCalendar,
+    /// state of the market as of the latest tick
+    session: SessionState,
+    session_prices: HashMap<Symbol, SessionPrices>,
+    average_trackers: HashMap<Symbol, Vec<AverageTracker>>,
+    trend_trackers: HashMap<Symbol, Box<dyn TrendDetector>>,
+    /// decimal places the prices of stocks are rounded to as they get listed
+    default_precision: u32,
+    /// decimal places every price of a stock is rounded to
+    precisions: HashMap<Symbol, u32>,
+    /// currency every price of a stock is quoted in
+    currencies: HashMap<Symbol, Currency>,
+    /// listed symbols that are fx rates
+    fx_pairs: HashMap<Symbol, FxPair>,
+    lowest: HashMap<Symbol, Option<Price>>,
+    highest: HashMap<Symbol, Option<Price>>,
+    data: HashMap<Symbol, PriceHistory>,
+    summaries: HashMap<Symbol, Option<StockSummary>>,
+    candles: HashMap<Symbol, CandleAggregator>,
+    volumes: HashMap<Symbol, VolumeStats>,
+    quotes: HashMap<Symbol, Quote>,
+    books: HashMap<Symbol, OrderBook>,
+    /// stock of every order resting in a book
+    order_stocks: HashMap<OrderId, Symbol>,
+    next_order_id: OrderId,
+    models: HashMap<Symbol, Box<dyn PriceModel>>,
+    volume_models: HashMap<Symbol, Box<dyn VolumeModel>>,
 }
 
 impl StockData {
+    /// lists the default stocks, all of them priced by the UniformModel
+    /// and traded in LogNormalVolume sizes
     pub fn initialize() -> Self {
-        let mut data = HashMap::new();
-        let mut highest = HashMap::new();
-        let mut lowest = HashMap::new();
-        let mut summaries = HashMap::new();
+        Self::with_retention(RetentionPolicy::default())
+    }
+
+    /// same as initialize, with a custom retention policy for the price history
+    pub fn with_retention(retention: RetentionPolicy) -> Self {
+        let mut stock_data = StockData {
+            registry: SymbolRegistry::default(),
+            retention,
+            averages: MovingAverage::DEFAULTS.to_vec(),
+            trend_detector: Box::new(QuarterComparison::default()),
+            spread_model: Box::new(RelativeSpread::default()),
+            correlation: None,
+            random_jumps: None,
+            scheduled_jumps: vec![],
+            circuit_breaker: None,
+            market_index: MarketIndex::default(),
+            halts: HashMap::new(),
+            scheduled_actions: vec![],
+            adjustments: HashMap::new(),
+            calendar: TradingCalendar::always_open(),
+            session: SessionState::Regular,
+            session_prices: HashMap::new(),
+            average_trackers: HashMap::new(),
+            trend_trackers: HashMap::new(),
+            default_precision: DEFAULT_SCALE,
+            precisions: HashMap::new(),
+            currencies: HashMap::new(),
+            fx_pairs: HashMap::new(),
+            lowest: HashMap::new(),
+            highest: HashMap::new(),
+            data: HashMap::new(),
+            summaries: HashMap::new(),
+            candles: HashMap::new(),
+            volumes: HashMap::new(),
+            quotes: HashMap::new(),
+            books: HashMap::new(),
+            order_stocks: HashMap::new(),
+            next_order_id: 1,
+            models: HashMap::new(),
+            volume_models: HashMap::new(),
+        };
+
+        for stock in STOCKS {
+            stock_data
+                .add_stock(stock, Box::new(UniformModel::default()))
+                .expect("default stocks are valid and unique");
+        }
+
+        stock_data
+    }
+
+    /// get all listed stocks, in listing order
+    pub fn get_stocks(&self) -> &[Symbol] {
+        self.registry.list()
+    }
+
+    /// lists a new stock priced by the given model, it starts ticking on the next tick,
+    /// trade sizes come from the default LogNormalVolume until set_volume_model is called
+    pub fn add_stock(
+        &mut self,
+        stock: &str,
+        model: Box<dyn PriceModel>,
+    ) -> Result<Symbol, RegistryError> {
+        let stock = self.registry.add(stock)?;
+
+        self.data
+            .insert(stock.clone(), PriceHistory::new(self.retention));
+        self.average_trackers.insert(
+            stock.clone(),
+            self.averages
+                .iter()
+                .copied()
+                .map(AverageTracker::new)
+                .collect(),
+        );
+        self.trend_trackers
+            .insert(stock.clone(), self.trend_detector.fresh());
+        self.precisions
+            .insert(stock.clone(), self.default_precision);
+        self.currencies.insert(stock.clone(), BASE_CURRENCY.into());
+        self.lowest.insert(stock.clone(), None);
+        self.highest.insert(stock.clone(), None);
+        self.summaries.insert(stock.clone(), None);
+        self.candles
+            .insert(stock.clone(), CandleAggregator::default());
+        self.volumes.insert(stock.clone(), VolumeStats::default());
+        self.session_prices
+            .insert(stock.clone(), SessionPrices::default());
+        self.adjustments.insert(stock.clone(), vec![]);
+        self.books.insert(stock.clone(), OrderBook::default());
+        self.models.insert(stock.clone(), model);
+        self.volume_models
+            .insert(stock.clone(), Box::new(LogNormalVolume::default()));
+
+        Ok(stock)
+    }
+
+    /// lists the given stocks with the UniformModel unless they already are,
+    /// replacing delists every other stock
+    pub fn list_stocks(
+        &mut self,
+        stocks: &[Symbol],
+        mode: ImportMode,
+    ) -> Result<(), RegistryError> {
+        if mode == ImportMode::Replace {
+            let listed = self.registry.list().to_vec();
+            for stock in listed.iter().filter(|stock| !stocks.contains(stock)) {
+                self.delist_stock(stock)?;
+            }
+        }
+        for stock in stocks {
+            if !self.registry.contains(stock) {
+                self.add_stock(stock, Box::new(UniformModel::default()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// records imported prices as ticks so summaries, trends and indicators start out warm,
+    /// unknown symbols are listed with the UniformModel, prices older than the last tick
+    /// of their stock are skipped, returns how many ticks were recorded
+    pub fn import_prices(
+        &mut self,
+        mut prices: Vec<ImportedPrice>,
+        mode: ImportMode,
+    ) -> Result<usize, ImportError> {
+        let mut stocks: Vec<Symbol> = vec![];
+        for price in &prices {
+            if !stocks.contains(&price.stock) {
+                stocks.push(price.stock.clone());
+            }
+        }
+        self.list_stocks(&stocks, mode)?;
+
+        prices.sort_by_key(|price| price.timestamp);
+        let mut recorded = 0;
+        for price in prices {
+            let last_tick = self.get_last_tick(&price.stock);
+            if last_tick.is_some_and(|tick| tick.timestamp > price.timestamp) {
+                continue;
+            }
+
+            let tick = Tick {
+                seq: last_tick.map_or(1, |tick| tick.seq + 1),
+                timestamp: price.timestamp,
+                price: price.price.round_to(self.precision(&price.stock)),
+                volume: price.volume,
+            };
+            self.record_tick(&price.stock, tick);
+            recorded += 1;
+        }
+
+        Ok(recorded)
+    }
+
+    /// records a price that traded elsewhere, e.g. in a recording being replayed, and quotes
+    /// around it, halts and market hours do not apply since the recording already saw them
+    pub fn replay_price<R: RngCore>(
+        &mut self,
+        price: &ImportedPrice,
+        rng: &mut R,
+    ) -> Result<Tick, RegistryError> {
+        if !self.registry.contains(&price.stock) {
+            return Err(RegistryError::NotListed(price.stock.clone()));
+        }
+
+        let tick = Tick {
+            seq: self
+                .get_last_tick(&price.stock)
+                .map_or(1, |tick| tick.seq + 1),
+            timestamp: price.timestamp,
+            price: price.price.round_to(self.precision(&price.stock)),
+            volume: price.volume,
+        };
+        self.record_tick(&price.stock, tick);
+        let quote = self.spread_model.quote(tick, rng);
+        self.quotes.insert(price.stock.clone(), quote);
+
+        Ok(tick)
+    }
+
+    /// delists a stock and drops everything recorded for it
+    pub fn delist_stock(&mut self, stock: &str) -> Result<Symbol, RegistryError> {
+        let stock = self.registry.delist(stock)?;
+
+        self.data.remove(&stock);
+        self.average_trackers.remove(&stock);
+        self.trend_trackers.remove(&stock);
+        self.precisions.remove(&stock);
+        self.currencies.remove(&stock);
+        self.fx_pairs.remove(&stock);
+        self.lowest.remove(&stock);
+        self.highest.remove(&stock);
+        self.summaries.remove(&stock);
+        self.candles.remove(&stock);
+        self.volumes.remove(&stock);
+        self.session_prices.remove(&stock);
+        self.quotes.remove(&stock);
+        self.books.remove(&stock);
+        self.halts.remove(&stock);
+        self.scheduled_jumps.retain(|jump| jump.stock != stock);
+        self.scheduled_actions
+            .retain(|action| action.stock != stock);
+        self.adjustments.remove(&stock);
+        self.order_stocks
+            .retain(|_, order_stock| order_stock != &stock);
+        self.models.remove(&stock);
+        self.volume_models.remove(&stock);
+
+        Ok(stock)
+    }
+
+    /// sets the windowed averages reported in every summary, applies from the next tick,
+    /// the new averages are caught up on the retained prices right away
+    pub fn set_averages(&mut self, averages: Vec<MovingAverage>) {
+        for (stock, trackers) in &mut self.average_trackers {
+            *trackers = averages.iter().copied().map(AverageTracker::new).collect();
+            if let Some(history) = self.data.get(stock) {
+                for price in history.prices() {
+                    trackers.iter_mut().for_each(|tracker| tracker.push(*price));
+                }
+            }
+        }
+        self.averages = averages;
+    }
+
+    /// sets the detector that decides the trend in every summary, applies from the next tick,
+    /// the new detector is caught up on the retained prices right away
+    pub fn set_trend_detector(&mut self, trend_detector: Box<dyn TrendDetector>) {
+        for (stock, tracker) in &mut self.trend_trackers {
+            *tracker = trend_detector.fresh();
+            if let Some(history) = self.data.get(stock) {
+                for price in history.prices() {
+                    tracker.push(*price);
+                }
+            }
+        }
+        self.trend_detector = trend_detector;
+    }
+
+    /// sets the model that quotes the best bid and ask of every stock, applies from the next tick
+    pub fn set_spread_model(&mut self, spread_model: Box<dyn SpreadModel>) {
+        self.spread_model = spread_model;
+    }
+
+    /// correlates the price shocks of the stocks covered by the matrix, None makes every
+    /// stock move independently again, applies from the next tick
+    pub fn set_correlation(&mut self, correlation: Option<CorrelatedShocks>) {
+        self.correlation = correlation;
+    }
+
+    /// lets every tick of every stock jump at random, None turns random jumps off
+    pub fn set_random_jumps(&mut self, random_jumps: Option<RandomJumps>) {
+        self.random_jumps = random_jumps;
+    }
+
+    /// jumps the price of a stock on its first tick at or after the jump's timestamp,
+    /// returns the jump as scheduled, for the stock the way it was listed
+    pub fn schedule_jump(&mut self, jump: ScheduledJump) -> Result<ScheduledJump, RegistryError> {
+        let jump = ScheduledJump {
+            stock: self.registry.get(&jump.stock)?,
+            ..jump
+        };
+        self.scheduled_jumps.push(jump.clone());
+        Ok(jump)
+    }
+
+    /// applies a split or dividend on the first tick of a stock at or after the action's
+    /// timestamp, returns the action as scheduled, for the stock the way it was listed
+    pub fn schedule_action(
+        &mut self,
+        action: ScheduledAction,
+    ) -> Result<ScheduledAction, RegistryError> {
+        let action = ScheduledAction {
+            stock: self.registry.get(&action.stock)?,
+            ..action
+        };
+        self.scheduled_actions.push(action.clone());
+        Ok(action)
+    }
+
+    /// the symbol of a listed stock the way it was listed
+    pub fn get_symbol(&self, stock: &str) -> Result<Symbol, RegistryError> {
+        self.registry.get(stock)
+    }
+
+    /// halts every stock when the market index falls too far, None turns the breaker off
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Option<CircuitBreaker>) {
+        self.circuit_breaker = circuit_breaker;
+        self.market_index.reset();
+    }
+
+    /// sets the market hours, stocks only tick while the market is open
+    /// and take orders outside of closed hours
+    pub fn set_calendar(&mut self, calendar: TradingCalendar) {
+        self.session = calendar.state_at(now());
+        self.calendar = calendar;
+    }
+
+    pub fn get_calendar(&self) -> &TradingCalendar {
+        &self.calendar
+    }
+
+    /// state of the market as of the latest tick
+    pub fn get_session(&self) -> SessionState {
+        self.session
+    }
+
+    /// everything recorded for the listed stocks as of now
+    pub fn snapshot(&self) -> Snapshot {
+        let stocks = self
+            .get_stocks()
+            .iter()
+            .map(|stock| StockSnapshot {
+                stock: stock.clone(),
+                precision: self.precision(stock),
+                currency: self.currencies[stock].clone(),
+                history: self.data[stock].snapshot(),
+                lowest: self.get_lowest_price(stock),
+                highest: self.get_highest_price(stock),
+                summary: self.summaries[stock].clone(),
+                candles: self.candles[stock].clone(),
+                volume: self.volumes[stock],
+                session_prices: self.session_prices[stock],
+                adjustments: self.adjustments[stock].clone(),
+                halt: self.get_halt(stock),
+            })
+            .collect();
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at: now(),
+            stocks,
+            scheduled_jumps: self.scheduled_jumps.clone(),
+            scheduled_actions: self.scheduled_actions.clone(),
+        }
+    }
+
+    /// lists the stocks of a snapshot in place of the listed ones and picks up where they
+    /// left off, restored stocks are priced by the UniformModel until set_price_model is called,
+    /// the trackers are caught up on the retained prices and the history is kept under
+    /// the current retention policy
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+        }
+
+        let stocks: Vec<Symbol> = snapshot
+            .stocks
+            .iter()
+            .map(|saved| saved.stock.clone())
+            .collect();
+        self.list_stocks(&stocks, ImportMode::Replace)?;
+
+        for saved in &snapshot.stocks {
+            let stock = saved.stock.clone();
+            self.precisions.insert(stock.clone(), saved.precision);
+            self.currencies
+                .insert(stock.clone(), saved.currency.clone());
+            self.data.insert(
+                stock.clone(),
+                PriceHistory::restore(self.retention, &saved.history),
+            );
+            self.lowest.insert(stock.clone(), saved.lowest);
+            self.highest.insert(stock.clone(), saved.highest);
+            self.candles.insert(stock.clone(), saved.candles.clone());
+            self.volumes.insert(stock.clone(), saved.volume);
+            self.session_prices
+                .insert(stock.clone(), saved.session_prices);
+            self.adjustments
+                .insert(stock.clone(), saved.adjustments.clone());
+            match saved.halt {
+                Some(halt) => self.halts.insert(stock.clone(), halt),
+                None => self.halts.remove(&stock),
+            };
+            self.catch_up_trackers(&stock);
+            self.summaries.insert(stock, saved.summary.clone());
+        }
+        self.scheduled_jumps = snapshot.scheduled_jumps.clone();
+        self.scheduled_actions = snapshot.scheduled_actions.clone();
+        self.market_index.reset();
+
+        Ok(())
+    }
+
+    /// applies a journal entry on top of what was restored, entries that are already in are
+    /// skipped, ticks of stocks that are not listed are dropped and listed stocks are priced
+    /// by the UniformModel, returns whether the entry was applied
+    pub fn apply_journal_entry(&mut self, entry: &JournalEntry) -> bool {
+        match entry {
+            JournalEntry::Tick { stock, tick } => {
+                if !self.registry.contains(stock)
+                    || self
+                        .get_last_tick(stock)
+                        .is_some_and(|last| last.seq >= tick.seq)
+                {
+                    return false;
+                }
+
+                // ticks from before a corporate action that already took effect are adjusted for it,
+                // one action at a time so the price is rounded just like it was when adjusting
+                let adjustments = self.get_adjustments(stock).unwrap_or_default();
+                let (_, volume_factor) = Adjustment::since(adjustments, tick.timestamp);
+                let adjusted = Tick {
+                    price: adjustments
+                        .iter()
+                        .filter(|adjustment| adjustment.ex_date > tick.timestamp)
+                        .fold(tick.price, |price, adjustment| adjustment.price(price)),
+                    volume: (tick.volume as f64 * volume_factor).round() as u64,
+                    ..*tick
+                };
+                // the tick took the jump that was due, if any
+                if let Some(index) = self.due_jump(stock, tick.timestamp) {
+                    self.scheduled_jumps.remove(index);
+                }
+                self.record_adjusted_tick(stock, adjusted, *tick);
+                true
+            }
+            JournalEntry::Adjustment { stock, adjustment } => {
+                let applied = match self.get_adjustments(stock) {
+                    Some(adjustments) => adjustments.contains(adjustment),
+                    None => true,
+                };
+                if !applied {
+                    if let Some((index, _)) = self.due_action(stock, adjustment.ex_date) {
+                        self.scheduled_actions.remove(index);
+                    }
+                    self.adjust(stock, adjustment);
+                }
+                !applied
+            }
+            JournalEntry::JumpScheduled { jump } => {
+                let scheduled =
+                    self.registry.contains(&jump.stock) && !self.scheduled_jumps.contains(jump);
+                if scheduled {
+                    self.scheduled_jumps.push(jump.clone());
+                }
+                scheduled
+            }
+            JournalEntry::ActionScheduled { action } => {
+                let scheduled = self.registry.contains(&action.stock)
+                    && !self.scheduled_actions.contains(action);
+                if scheduled {
+                    self.scheduled_actions.push(action.clone());
+                }
+                scheduled
+            }
+            JournalEntry::Listed { stock, precision } => {
+                let listed = self
+                    .add_stock(stock, Box::new(UniformModel::default()))
+                    .is_ok();
+                listed && precision.is_none_or(|places| self.set_precision(stock, places))
+            }
+            JournalEntry::Delisted { stock } => self.delist_stock(stock).is_ok(),
+            JournalEntry::SnapshotTaken { .. } => false,
+        }
+    }
+
+    /// stops the ticks and order matching of a stock, for `duration` or until resumed
+    pub fn halt_stock(
+        &mut self,
+        stock: &str,
+        duration: Option<Duration>,
+    ) -> Result<Halt, RegistryError> {
+        self.halt_stock_at(stock, HaltReason::Manual, now(), duration)
+    }
+
+    /// same as halt_stock, starting at the given timestamp
+    pub fn halt_stock_at(
+        &mut self,
+        stock: &str,
+        reason: HaltReason,
+        since: Timestamp,
+        duration: Option<Duration>,
+    ) -> Result<Halt, RegistryError> {
+        let stock = self.registry.get(stock)?;
+        let halt = Halt {
+            reason,
+            since,
+            until: duration.map(|duration| since + duration.as_millis() as Timestamp),
+        };
+        self.halts.insert(stock, halt);
+        Ok(halt)
+    }
+
+    /// lets a halted stock trade again, returns the halt that was lifted if there was one
+    pub fn resume_stock(&mut self, stock: &str) -> Result<Option<Halt>, RegistryError> {
+        let stock = self.registry.get(stock)?;
+        Ok(self.halts.remove(&stock))
+    }
+
+    /// get the halt of a stock, None while it trades
+    pub fn get_halt(&self, stock: &str) -> Option<Halt> {
+        self.halts.get(stock).copied()
+    }
+
+    pub fn is_halted(&self, stock: &str) -> bool {
+        self.halts.contains_key(stock)
+    }
+
+    /// replaces the price model of a given stock, returns false if the stock is unknown
+    pub fn set_price_model(&mut self, stock: &str, model: Box<dyn PriceModel>) -> bool {
+        match self.models.get_mut(stock) {
+            Some(current_model) => {
+                *current_model = model;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// replaces the volume model of a given stock, returns false if the stock is unknown
+    pub fn set_volume_model(&mut self, stock: &str, model: Box<dyn VolumeModel>) -> bool {
+        match self.volume_models.get_mut(stock) {
+            Some(current_model) => {
+                *current_model = model;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// asks the price model of each stock for its next price and adds it to the hash maps,
+    /// stocks are always visited in listing order so a seeded rng reproduces the same ticks,
+    /// stocks covered by the correlation get their shocks drawn together up front,
+    /// halted stocks do not tick and neither does anything while the market is closed,
+    /// in extended hours only a share of the stocks tick,
+    /// returns the corporate actions, jumps, halts, resumes and session changes that happened
+    pub fn generate_next_tick<R: RngCore>(&mut self, rng: &mut R) -> Vec<MarketEvent> {
+        self.generate_next_tick_at(rng, now())
+    }
+
+    /// same as generate_next_tick, with the prices recorded at the given timestamp
+    pub fn generate_next_tick_at<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        timestamp: Timestamp,
+    ) -> Vec<MarketEvent> {
+        let stocks = self.registry.list().to_vec();
+        let mut events = self.resume_expired_halts(&stocks, timestamp);
+
+        let session = self.calendar.state_at(timestamp);
+        if session != self.session {
+            self.session = session;
+            events.push(MarketEvent::SessionChanged { state: session });
+        }
+        if session == SessionState::Closed {
+            return events;
+        }
+        let extended_rate = self.calendar.extended_rate.clamp(0.0, 1.0);
+
+        let shocks = self
+            .correlation
+            .as_ref()
+            .map(|correlation| correlation.draw(rng));
+
+        for stock in &stocks {
+            let last_price = self.get_last_price(stock).map(Price::to_f64);
+            let shock = match (&self.correlation, &shocks) {
+                (Some(correlation), Some(shocks)) => correlation
+                    .symbols()
+                    .iter()
+                    .position(|symbol| symbol == stock)
+                    .map(|index| shocks[index]),
+                _ => None,
+            };
+            if self.is_halted(stock) || (session.is_extended() && !rng.gen_bool(extended_rate)) {
+                continue;
+            }
+            let mut next_price = match (self.models.get_mut(stock), shock) {
+                (Some(model), Some(shock)) => model.next_price_with_shock(last_price, shock, rng),
+                (Some(model), None) => model.next_price(last_price, rng),
+                (None, _) => continue,
+            };
+            if let Some(adjustment) = self.next_action(stock, timestamp) {
+                next_price *= adjustment.price_factor;
+                events.push(MarketEvent::CorporateAction {
+                    stock: stock.clone(),
+                    adjustment,
+                });
+            }
+            if let Some(size) = self.next_jump(stock, timestamp, rng) {
+                next_price *= 1.0 + size;
+                events.push(MarketEvent::Jump {
+                    stock: stock.clone(),
+                    size,
+                });
+            }
+            let next_price = Price::from_f64(next_price, self.precision(stock));
+            let volume = match self.volume_models.get_mut(stock) {
+                Some(model) => model.next_volume(next_price, rng),
+                None => 1,
+            };
+            let tick = Tick {
+                seq: self.get_last_tick(stock).map_or(1, |tick| tick.seq + 1),
+                timestamp,
+                price: next_price,
+                volume,
+            };
+            self.record_tick(stock, tick);
+            let quote = self.spread_model.quote(tick, rng);
+            self.quotes.insert(stock.clone(), quote);
+        }
+
+        events.extend(self.check_circuit_breaker(&stocks, timestamp));
+        events
+    }
+
+    /// lifts the halts that ran out by the given timestamp, in listing order
+    fn resume_expired_halts(
+        &mut self,
+        stocks: &[Symbol],
+        timestamp: Timestamp,
+    ) -> Vec<MarketEvent> {
+        let mut events = vec![];
+
+        for stock in stocks {
+            let expired = self
+                .halts
+                .get(stock)
+                .and_then(|halt| halt.until)
+                .is_some_and(|until| until <= timestamp);
+
+            if expired {
+                if let Some(halt) = self.halts.remove(stock) {
+                    if halt.reason == HaltReason::CircuitBreaker {
+                        self.market_index.reset();
+                    }
+                }
+                events.push(MarketEvent::Resumed {
+                    stock: stock.clone(),
+                });
+            }
+        }
+
+        events
+    }
+
+    /// relative jump for the next price of a stock, scheduled jumps that are due come first
+    fn next_jump<R: RngCore>(
+        &mut self,
+        stock: &str,
+        timestamp: Timestamp,
+        rng: &mut R,
+    ) -> Option<f64> {
+        match self.due_jump(stock, timestamp) {
+            Some(index) => Some(self.scheduled_jumps.remove(index).size),
+            None => self.random_jumps.and_then(|jumps| jumps.draw(rng)),
+        }
+    }
+
+    /// index of the first scheduled jump of a stock due at the given timestamp
+    fn due_jump(&self, stock: &str, timestamp: Timestamp) -> Option<usize> {
+        self.scheduled_jumps
+            .iter()
+            .position(|jump| jump.stock == stock && jump.at <= timestamp)
+    }
+
+    /// applies the first corporate action of a stock that is due, actions stay queued until
+    /// the stock has a price they can be applied to, dividends until it is above them
+    fn next_action(&mut self, stock: &str, timestamp: Timestamp) -> Option<Adjustment> {
+        let (due, adjustment) = self.due_action(stock, timestamp)?;
+        self.scheduled_actions.remove(due);
+
+        self.adjust(stock, &adjustment);
+        Some(adjustment)
+    }
+
+    /// index and adjustment of the first scheduled action of a stock that can take effect
+    /// at the given timestamp
+    fn due_action(&self, stock: &str, timestamp: Timestamp) -> Option<(usize, Adjustment)> {
+        let last_price = self.get_last_price(stock)?;
+        self.scheduled_actions
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| action.stock == stock && action.at <= timestamp)
+            .find_map(|(index, action)| {
+                Some((index, action.action.adjustment(timestamp, last_price)?))
+            })
+    }
+
+    /// adjusts everything recorded for a stock for a corporate action, the trackers are
+    /// caught up on the adjusted prices and the market index starts over so the price
+    /// change does not trip the circuit breaker
+    fn adjust(&mut self, stock: &str, adjustment: &Adjustment) {
+        if let Some(history) = self.data.get_mut(stock) {
+            history.adjust(adjustment);
+        }
+        if let Some(candles) = self.candles.get_mut(stock) {
+            candles.adjust(adjustment);
+        }
+        if let Some(volume) = self.volumes.get_mut(stock) {
+            volume.adjust(adjustment);
+        }
+        if let Some(quote) = self.quotes.get_mut(stock) {
+            quote.adjust(adjustment);
+        }
+        if let Some(book) = self.books.get_mut(stock) {
+            book.adjust(adjustment);
+        }
+        if let Some(session) = self.session_prices.get_mut(stock) {
+            session.adjust(adjustment);
+        }
+        for extreme in [self.lowest.get_mut(stock), self.highest.get_mut(stock)] {
+            if let Some(Some(price)) = extreme {
+                *price = adjustment.price(*price);
+            }
+        }
+        if let Some(adjustments) = self.adjustments.get_mut(stock) {
+            adjustments.push(*adjustment);
+        }
+
+        self.catch_up_trackers(stock);
+        self.market_index.reset();
+
+        let stock_summary = self.get_summary(stock);
+        if let Some(summary) = self.summaries.get_mut(stock) {
+            *summary = stock_summary;
+        }
+    }
+
+    /// starts the average and trend trackers of a stock over on its retained prices
+    fn catch_up_trackers(&mut self, stock: &str) {
+        let prices = self
+            .get_history(stock)
+            .map(|history| history.prices().to_vec())
+            .unwrap_or_default();
+        if let Some(trackers) = self.average_trackers.get_mut(stock) {
+            *trackers = self
+                .averages
+                .iter()
+                .copied()
+                .map(AverageTracker::new)
+                .collect();
+            for price in &prices {
+                trackers.iter_mut().for_each(|tracker| tracker.push(*price));
+            }
+        }
+        if let Some(tracker) = self.trend_trackers.get_mut(stock) {
+            *tracker = self.trend_detector.fresh();
+            prices.iter().for_each(|price| tracker.push(*price));
+        }
+    }
+
+    /// feeds the market index and halts every trading stock once it falls through the breaker,
+    /// the index stands still while the breaker is tripped
+    fn check_circuit_breaker(
+        &mut self,
+        stocks: &[Symbol],
+        timestamp: Timestamp,
+    ) -> Vec<MarketEvent> {
+        let breaker = match self.circuit_breaker {
+            Some(breaker) => breaker,
+            None => return vec![],
+        };
+        let tripped = self
+            .halts
+            .values()
+            .any(|halt| halt.reason == HaltReason::CircuitBreaker);
+        // fx rates are not part of the index
+        let prices: Vec<f64> = stocks
+            .iter()
+            .filter(|stock| !self.fx_pairs.contains_key(*stock))
+            .filter_map(|stock| self.get_last_price(stock))
+            .map(Price::to_f64)
+            .collect();
+        if tripped || prices.is_empty() {
+            return vec![];
+        }
+
+        let level = prices.iter().sum::<f64>() / prices.len() as f64;
+        let fall = self.market_index.push(timestamp, level, breaker.window);
+        if fall < breaker.threshold {
+            return vec![];
+        }
+
+        let trading: Vec<Symbol> = stocks
+            .iter()
+            .filter(|stock| !self.is_halted(stock) && !self.fx_pairs.contains_key(*stock))
+            .cloned()
+            .collect();
+
+        let mut events = vec![];
+        for stock in trading {
+            let until = Some(breaker.halt_for);
+            if let Ok(halt) =
+                self.halt_stock_at(&stock, HaltReason::CircuitBreaker, timestamp, until)
+            {
+                events.push(MarketEvent::Halted { stock, halt });
+            }
+        }
+        events
+    }
+
+    /// matches an order in the book of a given stock, every trade is recorded as a tick
+    pub fn submit_order(&mut self, stock: &str, order: NewOrder) -> Result<Execution, OrderError> {
+        self.submit_order_at(stock, order, now())
+    }
+
+    /// same as submit_order, with the trades recorded at the given timestamp,
+    /// limit prices with more decimal places than the stock has are turned down
+    pub fn submit_order_at(
+        &mut self,
+        stock: &str,
+        order: NewOrder,
+        timestamp: Timestamp,
+    ) -> Result<Execution, OrderError> {
+        if self.is_halted(stock) {
+            return Err(OrderError::Halted(stock.into()));
+        }
+        if self.calendar.state_at(timestamp) == SessionState::Closed {
+            return Err(OrderError::MarketClosed);
+        }
+        let precision = self
+            .get_precision(stock)
+            .ok_or_else(|| OrderError::NotListed(stock.into()))?;
+        if order.order_type == OrderType::Limit
+            && order
+                .price
+                .is_some_and(|price| price.round_to(precision) != price)
+        {
+            return Err(OrderError::InvalidPrice);
+        }
+        let order = NewOrder {
+            price: order.price.map(|price| price.round_to(precision)),
+            ..order
+        };
+        let book = self
+            .books
+            .get_mut(stock)
+            .ok_or_else(|| OrderError::NotListed(stock.into()))?;
+        let execution = book.submit(self.next_order_id, order, timestamp)?;
+        self.next_order_id += 1;
+
+        for trade in &execution.trades {
+            // makers that were filled completely have left the book
+            let book = self.books.get(stock);
+            if book.and_then(|book| book.order(trade.maker_id)).is_none() {
+                self.order_stocks.remove(&trade.maker_id);
+            }
+
+            let tick = Tick {
+                seq: self.get_last_tick(stock).map_or(1, |tick| tick.seq + 1),
+                timestamp,
+                price: trade.price,
+                volume: trade.quantity,
+            };
+            self.record_tick(stock, tick);
+        }
+
+        if execution.remaining > 0 && execution.status != OrderStatus::Cancelled {
+            self.order_stocks.insert(execution.order_id, stock.into());
+        }
+
+        Ok(execution)
+    }
+
+    /// takes a resting order out of its book
+    pub fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        let stock = self
+            .order_stocks
+            .get(&id)
+            .ok_or(OrderError::UnknownOrder(id))?;
+        let order = self
+            .books
+            .get_mut(stock)
+            .ok_or(OrderError::UnknownOrder(id))?
+            .cancel(id)?;
+        self.order_stocks.remove(&id);
+
+        Ok(order)
+    }
+
+    /// get the L2 depth of a stock's book, `levels` best prices per side
+    pub fn get_depth(&self, stock: &str, levels: usize) -> Option<Depth> {
+        self.books.get(stock).map(|book| book.depth(levels))
+    }
+
+    /// decimal places the prices of stocks listed from now on are rounded to,
+    /// at most MAX_SCALE
+    pub fn set_default_precision(&mut self, places: u32) {
+        self.default_precision = places.min(MAX_SCALE);
+    }
+
+    /// decimal places the prices of a stock are rounded to from now on, at most MAX_SCALE,
+    /// what was recorded keeps its decimal places, false if the stock is not listed
+    pub fn set_precision(&mut self, stock: &str, places: u32) -> bool {
+        match self.precisions.get_mut(stock) {
+            Some(precision) => {
+                *precision = places.min(MAX_SCALE);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// get the decimal places the prices of a stock are rounded to
+    pub fn get_precision(&self, stock: &str) -> Option<u32> {
+        self.precisions.get(stock).copied()
+    }
+
+    fn precision(&self, stock: &str) -> u32 {
+        self.get_precision(stock).unwrap_or(self.default_precision)
+    }
+
+    /// lists an fx pair as an instrument ticking its rate, priced by
+    /// `GeometricBrownianMotion::fx_preset` to FX_SCALE decimal places and quoted in
+    /// the quote currency of the pair, a pair that is already listed, e.g. restored,
+    /// is priced as one from now on
+    pub fn add_fx_pair(&mut self, pair: &FxPair) -> Result<Symbol, RegistryError> {
+        let symbol = pair.symbol();
+        let model = Box::new(GeometricBrownianMotion::fx_preset(&symbol));
+        if !self.set_price_model(&symbol, model.clone()) {
+            self.add_stock(&symbol, model)?;
+        }
+
+        self.set_precision(&symbol, FX_SCALE);
+        self.currencies.insert(symbol.clone(), pair.quote.clone());
+        self.fx_pairs.insert(symbol.clone(), pair.clone());
+        Ok(symbol)
+    }
+
+    /// get the fx pair a listed symbol ticks the rate of
+    pub fn get_fx_pair(&self, stock: &str) -> Option<&FxPair> {
+        self.fx_pairs.get(stock)
+    }
+
+    /// quotes the prices of a stock in a currency from now on, fx pairs stay quoted
+    /// in their quote currency, false if the stock is not listed or the code is not a currency
+    pub fn set_currency(&mut self, stock: &str, currency: &str) -> bool {
+        if !is_currency(currency) || self.fx_pairs.contains_key(stock) {
+            return false;
+        }
+        match self.currencies.get_mut(stock) {
+            Some(current) => {
+                *current = currency.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// get the currency the prices of a stock are quoted in
+    pub fn get_currency(&self, stock: &str) -> Option<&str> {
+        self.currencies.get(stock).map(String::as_str)
+    }
+
+    /// how many of `to` one `from` is worth as of the latest fx ticks, from a listed pair
+    /// either way round or crossed through BASE_CURRENCY, None without the rates to do so
+    pub fn get_rate(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        self.pair_rate(from, to).or_else(|| {
+            Some(self.pair_rate(from, BASE_CURRENCY)? * self.pair_rate(BASE_CURRENCY, to)?)
+        })
+    }
+
+    /// how prices in `from` are converted to `to`, None without the rates to do so
+    pub fn get_conversion(&self, from: &str, to: &str) -> Option<Conversion> {
+        if from == to {
+            return Some(Conversion::NONE);
+        }
+        Some(Conversion::to(to, self.get_rate(from, to)?))
+    }
+
+    /// rate of a listed pair or the inverse of the opposite one
+    fn pair_rate(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        let rate = |base: &str, quote: &str| {
+            let pair = self
+                .fx_pairs
+                .values()
+                .find(|pair| pair.base == base && pair.quote == quote)?;
+            self.get_last_price(&pair.symbol())
+                .map(Price::to_f64)
+                .filter(|rate| *rate > 0.0)
+        };
+
+        rate(from, to).or_else(|| rate(to, from).map(|rate| 1.0 / rate))
+    }
+
+    /// get the summary of a stock with its prices converted to a currency
+    pub fn get_summary_in(&self, stock: &str, currency: &str) -> Option<StockSummary> {
+        let summary = self.summaries.get(stock)?.as_ref()?;
+        let conversion = self.get_conversion(self.get_currency(stock)?, currency)?;
+        Some(summary.converted(conversion))
+    }
+
+    /// get all sumarries
+    pub fn get_summaries(&self) -> &HashMap<Symbol, Option<StockSummary>> {
+        &self.summaries
+    }
+
+    /// get last recorded price for a stock
+    pub fn get_last_price(&self, stock: &str) -> Option<Price> {
+        self.get_history(stock)
+            .and_then(|history| history.last_price())
+    }
+
+    /// get last recorded tick for a stock
+    pub fn get_last_tick(&self, stock: &str) -> Option<Tick> {
+        self.get_history(stock)
+            .and_then(|history| history.last_tick())
+    }
+
+    /// get the latest best bid and ask of a stock
+    pub fn get_quote(&self, stock: &str) -> Option<Quote> {
+        self.quotes.get(stock).copied()
+    }
+
+    /// get the opening and closing prices of the latest regular sessions of a stock
+    pub fn get_session_prices(&self, stock: &str) -> Option<SessionPrices> {
+        self.session_prices.get(stock).copied()
+    }
+
+    /// get the traded volume of a stock, overall and in the current session
+    pub fn get_volume(&self, stock: &str) -> Option<VolumeStats> {
+        self.volumes.get(stock).copied()
+    }
+
+    /// get the tick of a stock that was current at the given timestamp,
+    /// only ticks that are still in the retention window can be found
+    pub fn get_tick_at(&self, stock: &str, timestamp: Timestamp) -> Option<Tick> {
+        self.get_history(stock)
+            .and_then(|history| history.tick_at(timestamp))
+    }
+
+    /// get the retained price history of a stock, along with the roll ups of older prices
+    pub fn get_history(&self, stock: &str) -> Option<&PriceHistory> {
+        self.data.get(stock)
+    }
+
+    /// corporate actions that took effect for a stock, oldest first
+    pub fn get_adjustments(&self, stock: &str) -> Option<&[Adjustment]> {
+        self.adjustments.get(stock).map(Vec::as_slice)
+    }
+
+    /// retained ticks of a stock at the prices and volumes they traded at,
+    /// before any adjustment for later corporate actions
+    pub fn get_raw_ticks(&self, stock: &str) -> Option<Vec<Tick>> {
+        self.get_raw_ticks_since(stock, 0)
+    }
 
-        for stock in STOCKS {
-            data.insert(stock, vec![]);
-            lowest.insert(stock, None);
-            highest.insert(stock, None);
-            summaries.insert(stock, None);
-        }
+    /// ticks recorded for a stock after a sequence number, at the prices they traded at
+    pub fn get_raw_ticks_since(&self, stock: &str, seq: u64) -> Option<Vec<Tick>> {
+        let ticks = self.get_history(stock)?.traded_ticks();
+        let start = ticks.partition_point(|tick| tick.seq <= seq);
 
-        StockData {
-            lowest,
-            highest,
-            data,
-            summaries,
-        }
+        Some(ticks[start..].to_vec())
     }
 
-    /// randomly generates new price for each stock and adds it to the hash maps
-    pub fn generate_next_tick(&mut self, thread_rng: &mut ThreadRng) {
-        let next_prices: [Price; STOCKS.len()] = thread_rng.gen();
-        let mut iter = next_prices.iter().map(|v| v * 100f64);
+    /// same as get_candles, before any adjustment for later corporate actions
+    pub fn get_raw_candles(
+        &self,
+        stock: &str,
+        interval: Interval,
+        limit: usize,
+    ) -> Option<Vec<Candle>> {
+        self.candles
+            .get(stock)
+            .map(|candles| candles.traded_candles(interval, limit))
+    }
 
-        for stock in STOCKS {
-            let next_price = iter.next().unwrap();
-            self.insert_next(stock, next_price);
-            self.insert_lowest(stock, next_price);
-            self.insert_highest(stock, next_price);
+    /// get the latest `limit` candles of a stock for the given interval, oldest first,
+    /// adjusted for corporate actions so they stay continuous across splits
+    pub fn get_candles(
+        &self,
+        stock: &str,
+        interval: Interval,
+        limit: usize,
+    ) -> Option<Vec<Candle>> {
+        self.candles
+            .get(stock)
+            .map(|candles| candles.candles(interval, limit))
+    }
 
-            let stock_summary = self.get_summary(stock);
-            self.summaries.insert(stock, stock_summary);
-        }
+    /// computes the given windowed averages over the retained prices of a stock,
+    /// rounded to its decimal places
+    pub fn get_moving_averages(
+        &self,
+        stock: &str,
+        averages: &[MovingAverage],
+    ) -> Option<BTreeMap<String, Option<Price>>> {
+        let precision = self.get_precision(stock)?;
+        self.get_history(stock).map(|history| {
+            averages
+                .iter()
+                .map(|average| {
+                    let value = average.compute(history.prices());
+                    let value = value.map(|value| Price::from_f64(value, precision));
+                    (average.to_string(), value)
+                })
+                .collect()
+        })
     }
 
-    /// get all sumarries
-    pub fn get_summaries(&self) -> &HashMap<&'static str, Option<StockSummary>> {
-        &self.summaries
+    /// get the policy the price history of every stock is retained under
+    pub fn get_retention(&self) -> RetentionPolicy {
+        self.retention
     }
 
-    /// get last recorded price for a stock
-    pub fn get_last_price(&self, stock: &str) -> Option<Price> {
-        if let Some(prices) = self.get_prices(stock) {
-            match prices.last() {
-                Some(price) => Some(*price),
-                None => None,
-            }
-        } else {
-            None
-        }
+    /// computes a technical indicator over the retained prices of a stock,
+    /// the inner option is None while there is not enough data for the period
+    pub fn get_indicator(
+        &self,
+        stock: &str,
+        indicator: Indicator,
+        period: usize,
+    ) -> Option<Option<IndicatorValue>> {
+        self.get_history(stock)
+            .map(|history| indicator.compute(history.prices(), period))
     }
 
-    /// get the Summary for a given stock    
+    /// get the Summary for a given stock from its running aggregates,
+    /// averages are rounded to the decimal places of the stock
     fn get_summary(&self, stock: &str) -> Option<StockSummary> {
-        if let Some(current_prices) = self.get_prices(stock) {
-            let moving_avg = moving_average(current_prices);
-            let trend = get_trend(current_prices);
+        if let Some(history) = self.get_history(stock) {
+            let precision = self.get_precision(stock)?;
+            let round = |value: f64| Price::from_f64(value, precision);
+            let moving_avg = round(history.average());
+            let trend = self.trend_trackers.get(stock)?.reading();
+            let volume = self.get_volume(stock)?;
+            let session = self.get_session_prices(stock)?;
+            let last_price = self.get_last_price(stock);
             Some(StockSummary {
                 trend,
                 lowest_price: self.get_lowest_price(stock),
                 highest_price: self.get_highest_price(stock),
                 moving_average: moving_avg,
+                averages: self
+                    .average_trackers
+                    .get(stock)?
+                    .iter()
+                    .map(|tracker| (tracker.average().to_string(), tracker.value().map(round)))
+                    .collect(),
+                volume: volume.total,
+                session_volume: volume.session_volume,
+                vwap: volume.vwap().map(round),
+                previous_close: session.previous_close,
+                change_since_open: session.open.zip(last_price).map(|(open, last)| last - open),
             })
         } else {
             None
         }
     }
 
-    /// get history of all recorded prices for given stock
-    fn get_prices(&self, stock: &str) -> Option<&Vec<Price>> {
-        self.data.get(stock)
-    }
-
     /// get lowest recorded price for a given stock
     fn get_lowest_price(&self, stock: &str) -> Option<Price> {
         *self.lowest.get(stock).unwrap_or(&None)
@@ -118,40 +1340,79 @@ impl StockData {
         *self.highest.get(stock).unwrap_or(&None)
     }
 
-    /// inserts new value to the end of the vector of a given stock
-    fn insert_next(&mut self, stock: &'static str, price: Price) {
-        if let Some(current_prices) = self.data.get_mut(stock) {
-            current_prices.push(price);
+    /// records a tick of a given stock and brings its summary up to date
+    fn record_tick(&mut self, stock: &str, tick: Tick) {
+        self.record_adjusted_tick(stock, tick, tick);
+    }
+
+    /// records a tick adjusted for corporate actions that took effect after it traded,
+    /// the history and candles keep the tick as it traded as well
+    fn record_adjusted_tick(&mut self, stock: &str, tick: Tick, traded: Tick) {
+        self.insert_next(stock, tick, traded);
+        self.insert_lowest(stock, tick.price);
+        self.insert_highest(stock, tick.price);
+
+        let stock_summary = self.get_summary(stock);
+        if let Some(summary) = self.summaries.get_mut(stock) {
+            *summary = stock_summary;
+        }
+    }
+
+    /// inserts new value to the end of the history, into the candles, the traded volume,
+    /// the session prices and into the running averages and trend of a given stock
+    fn insert_next(&mut self, stock: &str, tick: Tick, traded: Tick) {
+        if let Some(history) = self.data.get_mut(stock) {
+            history.record(tick, traded);
+        }
+        if let Some(trackers) = self.average_trackers.get_mut(stock) {
+            trackers
+                .iter_mut()
+                .for_each(|tracker| tracker.push(tick.price.to_f64()));
+        }
+        if let Some(tracker) = self.trend_trackers.get_mut(stock) {
+            tracker.push(tick.price.to_f64());
+        }
+        if let Some(candles) = self.candles.get_mut(stock) {
+            candles.record(tick, traded);
+        }
+        if let Some(volume) = self.volumes.get_mut(stock) {
+            volume.push(tick);
+        }
+        if self.calendar.state_at(tick.timestamp) == SessionState::Regular {
+            let day = self.calendar.trading_day(tick.timestamp);
+            if let Some(session) = self.session_prices.get_mut(stock) {
+                session.push(day, tick.price);
+            }
         }
     }
 
     /// inserts new value for given stock if it's the lowest ever recorded
-    fn insert_lowest(&mut self, stock: &'static str, price: Price) {
-        if let Some(current_price) = self.lowest.get(stock) {
+    fn insert_lowest(&mut self, stock: &str, price: Price) {
+        if let Some(current_price) = self.lowest.get_mut(stock) {
             match current_price {
                 Some(v) => {
                     if price < *v {
-                        self.lowest.insert(stock, Some(price));
+                        *v = price;
                     }
                 }
                 None => {
-                    self.lowest.insert(stock, Some(price));
+                    *current_price = Some(price);
                 }
             };
         };
     }
 
     /// inserts new value for given stock if it's the highest ever recorded
-    fn insert_highest(&mut self, stock: &'static str, price: Price) {
-        if let Some(current_price) = self.highest.get(stock) {
+    fn insert_highest(&mut self, stock: &str, price: Price) {
+        if let Some(current_price) = self.highest.get_mut(stock) {
             match current_price {
                 Some(v) => {
                     if price > *v {
-                        self.highest.insert(stock, Some(price));
+                        *v = price;
                     }
                 }
                 None => {
-                    self.highest.insert(stock, Some(price));
+                    *current_price = Some(price);
                 }
             };
         };
@@ -162,6 +1423,10 @@ impl StockData {
 mod tests {
     use super::*;
 
+    fn price(text: &str) -> Price {
+        text.parse().unwrap()
+    }
+
     #[test]
     fn test_stock_data() {
         let mut stock_data = StockData::initialize();
@@ -178,25 +1443,25 @@ mod tests {
 
         assert!(stock_data.get_lowest_price(stock).is_some());
         assert!(stock_data.get_highest_price(stock).is_some());
-        assert_eq!(stock_data.data.get(stock).unwrap().len(), 1);
+        assert_eq!(stock_data.data.get(stock).unwrap().prices().len(), 1);
 
         // second tick happens
         stock_data.generate_next_tick(&mut thread_rng);
-        assert_eq!(stock_data.data.get(stock).unwrap().len(), 2);
+        assert_eq!(stock_data.data.get(stock).unwrap().prices().len(), 2);
 
         // 100 more ticks happen
         for _ in 0..100 {
             stock_data.generate_next_tick(&mut thread_rng);
         }
 
-        assert_eq!(stock_data.data.get(stock).unwrap().len(), 102);
+        assert_eq!(stock_data.data.get(stock).unwrap().prices().len(), 102);
 
         let lowest = stock_data.get_lowest_price(stock).unwrap();
         let highest = stock_data.get_highest_price(stock).unwrap();
 
-        for price in stock_data.data.get(stock).unwrap() {
-            assert!(lowest <= *price);
-            assert!(highest >= *price);
+        for tick in stock_data.data.get(stock).unwrap().ticks() {
+            assert!(lowest <= tick.price);
+            assert!(highest >= tick.price);
         }
 
         let summary = stock_data.get_summary(stock);
@@ -207,6 +1472,818 @@ mod tests {
         assert!(summary.lowest_price.is_some());
         assert_eq!(summary.highest_price.unwrap(), highest);
         assert_eq!(summary.lowest_price.unwrap(), lowest);
-        assert!(summary.moving_average > 0.0);
+        assert!(summary.moving_average.is_positive());
+    }
+
+    #[derive(Debug)]
+    struct ConstantModel(f64);
+
+    impl PriceModel for ConstantModel {
+        fn next_price(&mut self, _last_price: Option<f64>, _rng: &mut dyn rand::RngCore) -> f64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_set_price_model() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+
+        assert!(stock_data.set_price_model("APPL", Box::new(ConstantModel(42.0))));
+        assert!(!stock_data.set_price_model("NOPE", Box::new(ConstantModel(42.0))));
+
+        stock_data.generate_next_tick(&mut thread_rng);
+        stock_data.generate_next_tick(&mut thread_rng);
+
+        assert_eq!(stock_data.get_last_price("APPL"), Some(price("42")));
+        assert_eq!(stock_data.get_last_tick("APPL").unwrap().seq, 2);
+        assert_eq!(stock_data.data.get("APPL").unwrap().prices(), &[42.0, 42.0]);
+        assert_eq!(stock_data.data.get("GOOG").unwrap().prices().len(), 2);
+
+        // model prices are rounded to the decimal places of the stock
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(42.1289)));
+        stock_data.generate_next_tick(&mut thread_rng);
+        assert_eq!(
+            stock_data.get_last_price("APPL").unwrap().to_string(),
+            "42.13"
+        );
+        assert!(stock_data.set_precision("APPL", 0));
+        assert!(!stock_data.set_precision("NOPE", 0));
+        stock_data.generate_next_tick(&mut thread_rng);
+        assert_eq!(stock_data.get_last_price("APPL").unwrap().to_string(), "42");
+        assert_eq!(stock_data.get_precision("APPL"), Some(0));
+    }
+
+    #[test]
+    fn test_seeded_ticks_are_reproducible() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let run = |seed: u64| {
+            let mut stock_data = StockData::initialize();
+            stock_data.set_price_model("TSLA", Box::new(GeometricBrownianMotion::preset("TSLA")));
+            let mut rng = StdRng::seed_from_u64(seed);
+            for second in 0..50 {
+                stock_data.generate_next_tick_at(&mut rng, second * 1000);
+            }
+            stock_data.data
+        };
+
+        let first_run = run(7);
+        assert_eq!(first_run, run(7));
+        assert_ne!(first_run, run(8));
+    }
+
+    #[test]
+    fn test_add_and_delist_stock() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.generate_next_tick(&mut thread_rng);
+
+        let stock = stock_data
+            .add_stock("nflx", Box::new(ConstantModel(10.0)))
+            .unwrap();
+        assert_eq!(stock, "NFLX");
+        assert_eq!(stock_data.get_stocks().last().unwrap(), "NFLX");
+        assert!(stock_data.get_last_price("NFLX").is_none());
+        assert!(stock_data
+            .add_stock("NFLX", Box::new(ConstantModel(10.0)))
+            .is_err());
+
+        stock_data.generate_next_tick(&mut thread_rng);
+        assert_eq!(stock_data.get_last_price("NFLX"), Some(price("10")));
+        assert_eq!(stock_data.data.get("APPL").unwrap().prices().len(), 2);
+
+        assert_eq!(stock_data.delist_stock("APPL"), Ok("APPL".into()));
+        assert!(stock_data.get_last_price("APPL").is_none());
+        assert!(stock_data.get_summaries().get("APPL").is_none());
+        assert!(!stock_data.get_stocks().contains(&"APPL".to_string()));
+        assert_eq!(
+            stock_data.delist_stock("APPL"),
+            Err(RegistryError::NotListed("APPL".into()))
+        );
+
+        stock_data.generate_next_tick(&mut thread_rng);
+        assert!(!stock_data.data.contains_key("APPL"));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut stock_data = StockData::with_retention(RetentionPolicy {
+            capacity: 100,
+            ..RetentionPolicy::default()
+        });
+        let mut thread_rng = rand::thread_rng();
+
+        for second in 0..1000 {
+            stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+        }
+
+        let history = stock_data.get_history("APPL").unwrap();
+        assert_eq!(history.prices().len(), 100);
+        assert_eq!(history.ticks().len(), 100);
+
+        let tick = stock_data.get_tick_at("APPL", 950_500).unwrap();
+        assert_eq!((tick.seq, tick.timestamp), (951, 950_000));
+        assert!(stock_data.get_tick_at("APPL", 10_000).is_none());
+        assert_eq!(history.count(), 1000);
+        assert_eq!(history.rollups().len(), 15);
+
+        // lows and highs still cover the rolled up prices
+        let lowest = stock_data.get_lowest_price("APPL").unwrap();
+        assert!(history.rollups().iter().all(|rollup| rollup.low >= lowest));
+    }
+
+    #[test]
+    fn test_summary_averages() {
+        let mut stock_data = StockData::initialize();
+        stock_data.set_averages(vec!["sma3".parse().unwrap(), "ema3".parse().unwrap()]);
+        let mut thread_rng = rand::thread_rng();
+
+        stock_data.generate_next_tick(&mut thread_rng);
+        let summary = stock_data.get_summary("APPL").unwrap();
+        assert_eq!(summary.averages.get("SMA(3)"), Some(&None));
+        assert_eq!(summary.averages.get("EMA(3)"), Some(&None));
+
+        for _ in 0..9 {
+            stock_data.generate_next_tick(&mut thread_rng);
+        }
+        let prices = stock_data.get_history("APPL").unwrap().prices();
+        let summary = stock_data.get_summary("APPL").unwrap();
+        let sma = summary.averages["SMA(3)"].unwrap();
+        // averages are rounded to cents
+        let expected = prices[7..].iter().sum::<f64>() / 3.0;
+        assert!((sma.to_f64() - expected).abs() <= 0.005 + 1e-9);
+        assert_eq!(sma.scale(), 2);
+        assert!(summary.averages["EMA(3)"].is_some());
+        assert_eq!(summary.averages.len(), 2);
+
+        let wma = stock_data
+            .get_moving_averages("APPL", &["wma2".parse().unwrap()])
+            .unwrap();
+        let expected = (prices[8] + 2.0 * prices[9]) / 3.0;
+        assert!((wma["WMA(2)"].unwrap().to_f64() - expected).abs() <= 0.005 + 1e-9);
+        assert!(stock_data.get_moving_averages("NOPE", &[]).is_none());
+    }
+
+    #[test]
+    fn test_summary_trend_detector() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.generate_next_tick(&mut thread_rng);
+
+        let trend = stock_data.get_summary("APPL").unwrap().trend;
+        assert_eq!(trend.direction, StockTrend::NotEnoughData);
+        assert_eq!(trend.detector, "quarters(1000)");
+        assert_eq!(trend.confidence, 0.0);
+
+        stock_data.set_price_model(
+            "APPL",
+            Box::new(GeometricBrownianMotion {
+                volatility: 0.0,
+                ..GeometricBrownianMotion::preset("APPL")
+            }),
+        );
+        stock_data.set_trend_detector(parse_trend_detector("regression:10").unwrap());
+        for _ in 0..10 {
+            stock_data.generate_next_tick(&mut thread_rng);
+        }
+
+        let trend = stock_data.get_summary("APPL").unwrap().trend;
+        assert_eq!(trend.detector, "regression(10)");
+        assert_ne!(trend.direction, StockTrend::NotEnoughData);
+    }
+
+    #[derive(Debug)]
+    struct ConstantVolume(u64);
+
+    impl VolumeModel for ConstantVolume {
+        fn next_volume(&mut self, _price: Price, _rng: &mut dyn rand::RngCore) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_summary_volume_and_vwap() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+
+        assert!(stock_data.set_volume_model("APPL", Box::new(ConstantVolume(10))));
+        assert!(!stock_data.set_volume_model("NOPE", Box::new(ConstantVolume(10))));
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(10.0)));
+        for second in 0..3 {
+            stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+        }
+        stock_data.set_volume_model("APPL", Box::new(ConstantVolume(30)));
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(20.0)));
+        stock_data.generate_next_tick_at(&mut thread_rng, 3000);
+
+        let summary = stock_data.get_summary("APPL").unwrap();
+        assert_eq!(summary.volume, 60);
+        assert_eq!(summary.session_volume, 60);
+        assert_eq!(summary.vwap, Some(price("15")));
+        assert_eq!(stock_data.get_last_tick("APPL").unwrap().volume, 30);
+
+        // a new utc day starts a new session
+        stock_data.generate_next_tick_at(&mut thread_rng, Interval::OneDay.millis());
+        let summary = stock_data.get_summary("APPL").unwrap();
+        assert_eq!(summary.volume, 90);
+        assert_eq!(summary.session_volume, 30);
+        assert_eq!(summary.vwap, Some(price("20")));
+
+        let goog = stock_data.get_volume("GOOG").unwrap();
+        assert!(goog.total >= 5);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        assert!(stock_data.get_quote("APPL").is_none());
+
+        stock_data.set_spread_model(Box::new(FixedSpread {
+            spread: 0.5,
+            size: 10,
+        }));
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(42.0)));
+        stock_data.generate_next_tick_at(&mut thread_rng, 1000);
+
+        let quote = stock_data.get_quote("APPL").unwrap();
+        assert_eq!((quote.bid, quote.ask), (price("41.75"), price("42.25")));
+        assert_eq!(
+            (quote.bid_size, quote.ask_size, quote.timestamp),
+            (10, 10, 1000)
+        );
+
+        stock_data.delist_stock("APPL").unwrap();
+        assert!(stock_data.get_quote("APPL").is_none());
+    }
+
+    #[test]
+    fn test_orders_trade_into_ticks() {
+        let mut stock_data = StockData::initialize();
+
+        let resting = stock_data
+            .submit_order_at("APPL", NewOrder::limit(Side::Sell, price("50"), 100), 1000)
+            .unwrap();
+        assert_eq!(resting.status, OrderStatus::Open);
+        assert!(stock_data.get_last_tick("APPL").is_none());
+
+        let execution = stock_data
+            .submit_order_at("APPL", NewOrder::market(Side::Buy, 40), 2000)
+            .unwrap();
+        assert_eq!(execution.status, OrderStatus::Filled);
+        assert_ne!(execution.order_id, resting.order_id);
+
+        let tick = stock_data.get_last_tick("APPL").unwrap();
+        assert_eq!((tick.seq, tick.price, tick.volume), (1, price("50"), 40));
+        let summary = stock_data.get_summaries()["APPL"].clone().unwrap();
+        assert_eq!((summary.volume, summary.vwap), (40, Some(price("50"))));
+
+        // limit prices need to fit the decimal places of the stock
+        let finer = NewOrder::limit(Side::Sell, price("50.005"), 10);
+        assert_eq!(
+            stock_data.submit_order_at("APPL", finer, 2000),
+            Err(OrderError::InvalidPrice)
+        );
+        assert!(stock_data.set_precision("APPL", 3));
+        assert!(stock_data.submit_order_at("APPL", finer, 2000).is_ok());
+
+        let depth = stock_data.get_depth("APPL", 10).unwrap();
+        assert_eq!(depth.asks[0].quantity, 60);
+        assert!(stock_data.get_depth("NOPE", 10).is_none());
+
+        assert_eq!(
+            stock_data.cancel_order(resting.order_id).unwrap().remaining,
+            60
+        );
+        assert_eq!(
+            stock_data.cancel_order(resting.order_id),
+            Err(OrderError::UnknownOrder(resting.order_id))
+        );
+        assert_eq!(
+            stock_data.submit_order("NOPE", NewOrder::market(Side::Buy, 1)),
+            Err(OrderError::NotListed("NOPE".into()))
+        );
+    }
+
+    #[test]
+    fn test_correlated_ticks() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut stock_data = StockData::initialize();
+        let stocks = stock_data.get_stocks().to_vec();
+        for stock in &stocks {
+            stock_data.set_price_model(stock, Box::new(GeometricBrownianMotion::preset(stock)));
+        }
+        let matrix = CorrelationMatrix::parse(&stocks, "pair:GOOG:MSFT:0.95").unwrap();
+        stock_data.set_correlation(Some(matrix.cholesky().unwrap()));
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut same_direction = 0;
+        for second in 0..500 {
+            let before = (
+                stock_data.get_last_price("GOOG"),
+                stock_data.get_last_price("MSFT"),
+            );
+            stock_data.generate_next_tick_at(&mut rng, second * 1000);
+
+            if let (Some(goog), Some(msft)) = before {
+                let goog_up = stock_data.get_last_price("GOOG").unwrap() > goog;
+                let msft_up = stock_data.get_last_price("MSFT").unwrap() > msft;
+                if goog_up == msft_up {
+                    same_direction += 1;
+                }
+            }
+        }
+
+        // independent stocks would agree about half of the time
+        assert!(same_direction > 400, "{}", same_direction);
+    }
+
+    #[test]
+    fn test_halts_and_jumps() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.0)));
+
+        stock_data
+            .halt_stock_at("appl", HaltReason::Manual, 0, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert!(stock_data.halt_stock("NOPE", None).is_err());
+        assert_eq!(
+            stock_data.submit_order("APPL", NewOrder::market(Side::Buy, 1)),
+            Err(OrderError::Halted("APPL".into()))
+        );
+
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 1000);
+        assert!(events.is_empty());
+        assert!(stock_data.get_last_tick("APPL").is_none());
+        assert!(stock_data.get_last_tick("GOOG").is_some());
+
+        stock_data
+            .schedule_jump(ScheduledJump {
+                stock: "appl".into(),
+                at: 2000,
+                size: -0.1,
+            })
+            .unwrap();
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 2000);
+        assert_eq!(
+            events,
+            vec![
+                MarketEvent::Resumed {
+                    stock: "APPL".into()
+                },
+                MarketEvent::Jump {
+                    stock: "APPL".into(),
+                    size: -0.1
+                },
+            ]
+        );
+        assert_eq!(stock_data.get_last_price("APPL"), Some(price("90")));
+
+        stock_data.halt_stock("APPL", None).unwrap();
+        assert!(stock_data.is_halted("APPL"));
+        assert_eq!(
+            stock_data.resume_stock(" appl").unwrap().unwrap().until,
+            None
+        );
+        assert_eq!(stock_data.resume_stock("APPL"), Ok(None));
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        for stock in stock_data.get_stocks().to_vec() {
+            stock_data.set_price_model(&stock, Box::new(ConstantModel(100.0)));
+        }
+        stock_data.set_circuit_breaker(Some(CircuitBreaker {
+            threshold: 0.07,
+            window: Duration::from_secs(60),
+            halt_for: Duration::from_secs(10),
+        }));
+
+        stock_data.generate_next_tick_at(&mut thread_rng, 0);
+        for stock in stock_data.get_stocks().to_vec() {
+            stock_data.set_price_model(&stock, Box::new(ConstantModel(90.0)));
+        }
+
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 1000);
+        assert_eq!(events.len(), 6);
+        let halt = stock_data.get_halt("GOOG").unwrap();
+        assert_eq!(halt.reason, HaltReason::CircuitBreaker);
+        assert_eq!(halt.until, Some(11_000));
+
+        // nothing ticks while the market is halted, everything resumes together
+        stock_data.generate_next_tick_at(&mut thread_rng, 5000);
+        assert_eq!(stock_data.get_last_tick("GOOG").unwrap().seq, 2);
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 11_000);
+        assert_eq!(events.len(), 6);
+        assert!(!stock_data.is_halted("GOOG"));
+        assert_eq!(stock_data.get_last_tick("GOOG").unwrap().seq, 3);
+    }
+
+    #[test]
+    fn test_trading_sessions() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        let mut calendar = TradingCalendar::nyse();
+        calendar.extended_rate = 0.0;
+        stock_data.set_calendar(calendar);
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.0)));
+
+        // 2026-12-14 is a monday, new york is 5 hours behind utc in winter
+        let monday = 20_801 * Interval::OneDay.millis();
+        let hour = Interval::OneHour.millis();
+        let summary = |stock_data: &StockData| stock_data.get_summaries()["APPL"].clone().unwrap();
+
+        stock_data.generate_next_tick_at(&mut thread_rng, monday + 8 * hour);
+        assert_eq!(stock_data.get_session(), SessionState::Closed);
+        assert!(stock_data.get_last_tick("APPL").is_none());
+        assert_eq!(
+            stock_data.submit_order_at("APPL", NewOrder::market(Side::Buy, 1), monday),
+            Err(OrderError::MarketClosed)
+        );
+
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, monday + 14 * hour);
+        assert_eq!(
+            events,
+            vec![MarketEvent::SessionChanged {
+                state: SessionState::PreMarket
+            }]
+        );
+        assert!(stock_data.get_last_tick("APPL").is_none());
+
+        stock_data.generate_next_tick_at(&mut thread_rng, monday + 15 * hour);
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(110.0)));
+        stock_data.generate_next_tick_at(&mut thread_rng, monday + 16 * hour);
+        assert_eq!(stock_data.get_session(), SessionState::Regular);
+        assert_eq!(summary(&stock_data).previous_close, None);
+        assert_eq!(summary(&stock_data).change_since_open, Some(price("10")));
+
+        stock_data.generate_next_tick_at(&mut thread_rng, monday + 22 * hour);
+        assert_eq!(stock_data.get_session(), SessionState::AfterHours);
+        assert_eq!(
+            stock_data.get_last_tick("APPL").unwrap().timestamp,
+            monday + 16 * hour
+        );
+
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(120.0)));
+        stock_data.generate_next_tick_at(&mut thread_rng, monday + 39 * hour);
+        assert_eq!(summary(&stock_data).previous_close, Some(price("110")));
+        assert_eq!(summary(&stock_data).change_since_open, Some(Price::ZERO));
+    }
+
+    #[test]
+    fn test_corporate_actions() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.0)));
+        stock_data.set_volume_model("APPL", Box::new(ConstantVolume(10)));
+
+        let split = ScheduledAction {
+            stock: "APPL".into(),
+            at: 2_000,
+            action: CorporateAction::Split { ratio: 2.0 },
+        };
+        stock_data.schedule_action(split).unwrap();
+        stock_data
+            .schedule_action(ScheduledAction {
+                stock: "NOPE".into(),
+                at: 0,
+                action: CorporateAction::Dividend { amount: price("1") },
+            })
+            .unwrap_err();
+
+        stock_data.generate_next_tick_at(&mut thread_rng, 1_000);
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 2_000);
+        let adjustment = stock_data.get_adjustments("APPL").unwrap()[0];
+        assert_eq!(
+            events,
+            vec![MarketEvent::CorporateAction {
+                stock: "APPL".into(),
+                adjustment,
+            }]
+        );
+
+        // the adjusted series continues at the split price, the raw one keeps what traded
+        let history = stock_data.get_history("APPL").unwrap();
+        assert_eq!(history.prices(), &[50.0, 50.0]);
+        assert_eq!(history.ticks()[0].volume, 20);
+        let raw: Vec<(Price, u64)> = stock_data
+            .get_raw_ticks("APPL")
+            .unwrap()
+            .iter()
+            .map(|tick| (tick.price, tick.volume))
+            .collect();
+        assert_eq!(raw, vec![(price("100"), 10), (price("50"), 10)]);
+
+        let candles = stock_data
+            .get_candles("APPL", Interval::OneSecond, 10)
+            .unwrap();
+        assert_eq!(candles[0].close, candles[1].open);
+        let raw_candles = stock_data
+            .get_raw_candles("APPL", Interval::OneSecond, 10)
+            .unwrap();
+        assert_eq!(
+            (raw_candles[0].close, raw_candles[1].open),
+            (price("100"), price("50"))
+        );
+
+        let summary = stock_data.get_summaries()["APPL"].clone().unwrap();
+        assert_eq!(summary.highest_price, Some(price("50")));
+        assert_eq!(summary.moving_average, price("50"));
+        assert_eq!(summary.volume, 30);
+    }
+
+    #[test]
+    fn test_raw_prices() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.01)));
+        let split = ScheduledAction {
+            stock: "APPL".into(),
+            at: 2_000,
+            action: CorporateAction::Split { ratio: 3.0 },
+        };
+        stock_data.schedule_action(split).unwrap();
+        stock_data.generate_next_tick_at(&mut thread_rng, 1_000);
+        stock_data.generate_next_tick_at(&mut thread_rng, 2_000);
+
+        // a price that does not divide evenly still reads back as it traded
+        let history = stock_data.get_history("APPL").unwrap();
+        assert_eq!(history.ticks()[0].price, price("33.34"));
+        assert_eq!(history.traded_ticks()[0].price, price("100.01"));
+        let raw_candle = stock_data
+            .get_raw_candles("APPL", Interval::OneSecond, 10)
+            .unwrap()[0];
+        assert_eq!(
+            (raw_candle.low, raw_candle.close),
+            (price("100.01"), price("100.01"))
+        );
+
+        let restored = PriceHistory::restore(RetentionPolicy::default(), &history.snapshot());
+        assert_eq!(restored.traded_ticks(), history.traded_ticks());
+    }
+
+    #[test]
+    fn test_pending_actions() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.0)));
+        let dividend = ScheduledAction {
+            stock: "APPL".into(),
+            at: 0,
+            action: CorporateAction::Dividend {
+                amount: price("150"),
+            },
+        };
+        stock_data.schedule_action(dividend).unwrap();
+
+        // neither the first tick nor a price below the dividend can take it
+        let adjusted = |events: &[MarketEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, MarketEvent::CorporateAction { .. }))
+        };
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 1_000);
+        assert!(!adjusted(&events));
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(200.0)));
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 2_000);
+        assert!(!adjusted(&events));
+        assert_eq!(stock_data.scheduled_actions.len(), 1);
+
+        let events = stock_data.generate_next_tick_at(&mut thread_rng, 3_000);
+        assert!(adjusted(&events));
+        assert!(stock_data.scheduled_actions.is_empty());
+        assert_eq!(stock_data.get_last_price("APPL"), Some(price("50")));
+    }
+
+    #[test]
+    fn test_import_prices() {
+        let mut csv = String::from("symbol,timestamp,price,volume\n");
+        for second in 0..1001 {
+            csv += &format!("GOOG,{},{},10\n", second * 1000, 100 + second);
+        }
+        csv += "NFLX,5000,42,1\n";
+        let prices = parse_csv(csv.as_bytes()).unwrap();
+
+        let mut stock_data = StockData::initialize();
+        let recorded = stock_data
+            .import_prices(prices.clone(), ImportMode::Replace)
+            .unwrap();
+        assert_eq!(recorded, 1002);
+        assert_eq!(stock_data.get_stocks(), &["GOOG", "NFLX"]);
+
+        let summary = stock_data.get_summaries()["GOOG"].clone().unwrap();
+        assert_eq!(summary.trend.direction, StockTrend::Uptrend);
+        assert_eq!(summary.lowest_price, Some(price("100")));
+        assert_eq!(summary.highest_price, Some(price("1100")));
+        assert_eq!(summary.volume, 10_010);
+
+        // importing the same prices again only keeps the ones not older than the history
+        let recorded = stock_data.import_prices(prices, ImportMode::Seed).unwrap();
+        assert_eq!(recorded, 2);
+
+        stock_data.generate_next_tick_at(&mut rand::thread_rng(), 1_001_000);
+        assert_eq!(stock_data.get_last_tick("GOOG").unwrap().seq, 1003);
+
+        let mut stock_data = StockData::initialize();
+        stock_data
+            .import_prices(
+                parse_csv("NFLX,0,42\n".as_bytes()).unwrap(),
+                ImportMode::Seed,
+            )
+            .unwrap();
+        assert_eq!(stock_data.get_stocks().len(), 7);
+
+        let mut rng = rand::thread_rng();
+        let replayed = ImportedPrice {
+            stock: "NFLX".into(),
+            timestamp: 1_000,
+            price: price("43.125"),
+            volume: 5,
+        };
+        let tick = stock_data.replay_price(&replayed, &mut rng).unwrap();
+        // replayed prices are rounded to the decimal places of the stock
+        assert_eq!((tick.seq, tick.price.to_string()), (2, "43.13".into()));
+        assert_eq!(stock_data.get_quote("NFLX").unwrap().timestamp, 1_000);
+        let unlisted = ImportedPrice {
+            stock: "NOPE".into(),
+            ..replayed
+        };
+        assert!(stock_data.replay_price(&unlisted, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut stock_data = StockData::initialize();
+        let mut rng = rand::thread_rng();
+        for second in 0..100 {
+            stock_data.generate_next_tick_at(&mut rng, second * 1000);
+        }
+        stock_data.delist_stock("FB").unwrap();
+        stock_data
+            .halt_stock_at("TSLA", HaltReason::Manual, 99_000, None)
+            .unwrap();
+        let snapshot = stock_data.snapshot();
+        assert_eq!(snapshot.stocks.len(), 5);
+
+        let mut restored = StockData::initialize();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.get_stocks(), stock_data.get_stocks());
+        assert_eq!(restored.get_summaries(), stock_data.get_summaries());
+        assert_eq!(restored.get_halt("TSLA"), stock_data.get_halt("TSLA"));
+        assert_eq!(
+            restored.get_candles("APPL", Interval::OneMinute, 10),
+            stock_data.get_candles("APPL", Interval::OneMinute, 10)
+        );
+        assert_eq!(restored.snapshot().stocks, snapshot.stocks);
+
+        // ticking carries on from the restored history
+        restored.generate_next_tick_at(&mut rng, 100_000);
+        assert_eq!(restored.get_last_tick("APPL").unwrap().seq, 101);
+        assert_eq!(restored.get_history("APPL").unwrap().count(), 101);
+
+        let outdated = Snapshot {
+            version: SNAPSHOT_VERSION + 1,
+            ..snapshot
+        };
+        assert_eq!(
+            restored.restore(&outdated),
+            Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn test_fx_conversion() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        let eurusd = stock_data.add_fx_pair(&"EURUSD".parse().unwrap()).unwrap();
+        stock_data.add_fx_pair(&"USDJPY".parse().unwrap()).unwrap();
+        assert_eq!(stock_data.get_currency(&eurusd), Some("USD"));
+        assert_eq!(stock_data.get_precision(&eurusd), Some(FX_SCALE));
+        assert!(stock_data.get_rate("USD", "EUR").is_none());
+
+        stock_data.set_price_model(&eurusd, Box::new(ConstantModel(1.25)));
+        stock_data.set_price_model("USDJPY", Box::new(ConstantModel(150.0)));
+        stock_data.set_price_model("APPL", Box::new(ConstantModel(100.0)));
+        assert!(stock_data.set_currency("TSLA", "JPY"));
+        stock_data.set_precision("TSLA", 0);
+        stock_data.set_price_model("TSLA", Box::new(ConstantModel(1000.0)));
+        stock_data.generate_next_tick(&mut thread_rng);
+
+        assert_eq!(stock_data.get_rate("EUR", "USD"), Some(1.25));
+        assert_eq!(stock_data.get_rate("USD", "EUR"), Some(0.8));
+        // crossed through dollars
+        let eurjpy = stock_data.get_rate("EUR", "JPY").unwrap();
+        assert!((eurjpy - 187.5).abs() < 1e-9);
+        assert!(stock_data.get_rate("USD", "CHF").is_none());
+
+        let summary = stock_data.get_summary_in("APPL", "EUR").unwrap();
+        assert_eq!(summary.highest_price.unwrap().to_string(), "80.00");
+        assert!(stock_data.get_summary_in("APPL", "CHF").is_none());
+
+        assert!(stock_data.set_currency("GOOG", "EUR"));
+        assert!(!stock_data.set_currency(&eurusd, "GBP"));
+        assert!(!stock_data.set_currency("GOOG", "euro"));
+        let goog = stock_data.get_summary_in("GOOG", "EUR");
+        assert_eq!(goog, stock_data.get_summaries()["GOOG"].clone());
+
+        // whole yen converted to dollars get cents
+        let tsla = stock_data.get_summary_in("TSLA", "USD").unwrap();
+        assert_eq!(tsla.highest_price.unwrap().to_string(), "6.67");
+        let tick = stock_data.get_last_tick("TSLA").unwrap();
+        let conversion = stock_data.get_conversion("JPY", "USD").unwrap();
+        assert_eq!(tick.converted(conversion).price.to_string(), "6.67");
+    }
+
+    #[test]
+    fn test_journal_entries() {
+        let mut stock_data = StockData::initialize();
+        let mut thread_rng = rand::thread_rng();
+        stock_data
+            .schedule_action(ScheduledAction {
+                stock: "APPL".into(),
+                at: 15_000,
+                action: CorporateAction::Split { ratio: 2.0 },
+            })
+            .unwrap();
+
+        // what the engine journals, adjustments of a round before its ticks
+        let mut journal = vec![];
+        let mut snapshot = None;
+        for second in 0..20 {
+            if second == 10 {
+                for at in [12_000, 30_000] {
+                    let jump = ScheduledJump {
+                        stock: "TSLA".into(),
+                        at,
+                        size: 0.1,
+                    };
+                    let jump = stock_data.schedule_jump(jump).unwrap();
+                    journal.push(JournalEntry::JumpScheduled { jump });
+                }
+            }
+            let events = stock_data.generate_next_tick_at(&mut thread_rng, second * 1000);
+            for event in events {
+                if let MarketEvent::CorporateAction { stock, adjustment } = event {
+                    journal.push(JournalEntry::Adjustment { stock, adjustment });
+                }
+            }
+            for stock in stock_data.get_stocks() {
+                for tick in stock_data.get_raw_ticks_since(stock, second).unwrap() {
+                    journal.push(JournalEntry::Tick {
+                        stock: stock.clone(),
+                        tick,
+                    });
+                }
+            }
+            if second == 9 {
+                snapshot = Some(stock_data.snapshot());
+            }
+        }
+
+        // the split is still to come in the snapshot, the journal has it take effect
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.scheduled_actions.len(), 1);
+        let mut restored = StockData::initialize();
+        restored.restore(&snapshot).unwrap();
+        let applied = journal
+            .iter()
+            .filter(|entry| restored.apply_journal_entry(entry))
+            .count();
+        assert_eq!(applied, 6 * 10 + 1 + 2);
+        assert_eq!(restored.snapshot().stocks, stock_data.snapshot().stocks);
+        assert!(restored.scheduled_actions.is_empty());
+        assert_eq!(restored.scheduled_jumps, stock_data.scheduled_jumps);
+        assert_eq!(restored.scheduled_jumps[0].at, 30_000);
+
+        // ticks of a delisted stock are dropped, listing it again starts afresh
+        let tick = Tick {
+            seq: 100,
+            ..stock_data.get_last_tick("GOOG").unwrap()
+        };
+        let entries = [
+            JournalEntry::Delisted {
+                stock: "GOOG".into(),
+            },
+            JournalEntry::Tick {
+                stock: "GOOG".into(),
+                tick,
+            },
+            JournalEntry::Listed {
+                stock: "GOOG".into(),
+                precision: Some(3),
+            },
+        ];
+        let applied: Vec<bool> = entries
+            .iter()
+            .map(|entry| restored.apply_journal_entry(entry))
+            .collect();
+        assert_eq!(applied, [true, false, true]);
+        assert_eq!(restored.get_last_tick("GOOG"), None);
+        assert_eq!(restored.get_precision("GOOG"), Some(3));
     }
 }
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+};
+
+use crate::{Adjustment, Price, Symbol, Timestamp};
+
+/// exchange wide identifier of an order
+pub type OrderId = u64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderType {
+    /// rests in the book at its limit price until filled or cancelled
+    Limit,
+    /// takes whatever liquidity there is, the unfilled rest is cancelled
+    Market,
+}
+
+/// order as it is submitted, `price` is required for limit orders and ignored for market orders
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewOrder {
+    pub side: Side,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub price: Option<Price>,
+    pub quantity: u64,
+}
+
+impl NewOrder {
+    pub fn limit(side: Side, price: Price, quantity: u64) -> Self {
+        Self {
+            side,
+            order_type: OrderType::Limit,
+            price: Some(price),
+            quantity,
+        }
+    }
+
+    pub fn market(side: Side, quantity: u64) -> Self {
+        Self {
+            side,
+            order_type: OrderType::Market,
+            price: None,
+            quantity,
+        }
+    }
+}
+
+/// limit order resting in the book
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub id: OrderId,
+    pub side: Side,
+    pub price: Price,
+    pub quantity: u64,
+    /// quantity not filled yet
+    pub remaining: u64,
+    pub timestamp: Timestamp,
+}
+
+/// fill between an incoming order and a resting one, always at the resting order's price
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub price: Price,
+    pub quantity: u64,
+    pub timestamp: Timestamp,
+    pub maker_id: OrderId,
+    pub taker_id: OrderId,
+    /// side of the incoming order
+    pub taker_side: Side,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// resting in the book without any fill
+    Open,
+    /// some quantity filled, the rest is resting in the book
+    PartiallyFilled,
+    Filled,
+    /// market order whose unfilled rest was dropped
+    Cancelled,
+}
+
+/// outcome of submitting an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Execution {
+    pub order_id: OrderId,
+    pub status: OrderStatus,
+    pub trades: Vec<Trade>,
+    /// quantity left unfilled, resting in the book for limit orders
+    pub remaining: u64,
+}
+
+/// aggregated quantity at one price
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthLevel {
+    pub price: Price,
+    pub quantity: u64,
+    pub orders: usize,
+}
+
+/// L2 snapshot, best prices first on both sides
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Depth {
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum OrderError {
+    NotListed(Symbol),
+    Halted(Symbol),
+    MarketClosed,
+    InvalidQuantity,
+    InvalidPrice,
+    UnknownOrder(OrderId),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::NotListed(symbol) => write!(f, "{} is not listed", symbol),
+            OrderError::Halted(symbol) => write!(f, "trading in {} is halted", symbol),
+            OrderError::MarketClosed => write!(f, "the market is closed"),
+            OrderError::InvalidQuantity => write!(f, "quantity must be positive"),
+            OrderError::InvalidPrice => write!(
+                f,
+                "limit orders need a positive price within the decimal places of the stock"
+            ),
+            OrderError::UnknownOrder(id) => write!(f, "order {} is not in the book", id),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+/// Order Book
+/// limit orders of one stock matched in price-time priority,
+/// better prices fill first and orders at the same price fill in arrival order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Price, VecDeque<Order>>,
+    asks: BTreeMap<Price, VecDeque<Order>>,
+    /// where every resting order sits, for cancels
+    index: HashMap<OrderId, (Side, Price)>,
+}
+
+impl OrderBook {
+    /// matches the order against the opposite side and rests what is left of a limit order
+    pub fn submit(
+        &mut self,
+        id: OrderId,
+        order: NewOrder,
+        timestamp: Timestamp,
+    ) -> Result<Execution, OrderError> {
+        if order.quantity == 0 {
+            return Err(OrderError::InvalidQuantity);
+        }
+        let limit = match order.order_type {
+            OrderType::Limit => match order.price {
+                Some(price) if price.is_positive() => Some(price),
+                _ => return Err(OrderError::InvalidPrice),
+            },
+            OrderType::Market => None,
+        };
+
+        let mut remaining = order.quantity;
+        let mut trades = vec![];
+
+        while remaining > 0 {
+            let (level, resting) = match self.best_level_mut(order.side.opposite()) {
+                Some(best) => best,
+                None => break,
+            };
+            let crosses = match (limit, order.side) {
+                (None, _) => true,
+                (Some(limit), Side::Buy) => level <= limit,
+                (Some(limit), Side::Sell) => level >= limit,
+            };
+            if !crosses {
+                break;
+            }
+
+            let maker = resting.front_mut().expect("empty levels are removed");
+            let quantity = remaining.min(maker.remaining);
+            maker.remaining -= quantity;
+            remaining -= quantity;
+            trades.push(Trade {
+                price: maker.price,
+                quantity,
+                timestamp,
+                maker_id: maker.id,
+                taker_id: id,
+                taker_side: order.side,
+            });
+
+            if maker.remaining == 0 {
+                let maker_id = maker.id;
+                resting.pop_front();
+                let level_is_empty = resting.is_empty();
+                self.index.remove(&maker_id);
+                if level_is_empty {
+                    self.side_mut(order.side.opposite()).remove(&level);
+                }
+            }
+        }
+
+        let status = match (limit, remaining) {
+            (_, 0) => OrderStatus::Filled,
+            (None, _) => OrderStatus::Cancelled,
+            (Some(price), _) => {
+                self.side_mut(order.side)
+                    .entry(price)
+                    .or_default()
+                    .push_back(Order {
+                        id,
+                        side: order.side,
+                        price,
+                        quantity: order.quantity,
+                        remaining,
+                        timestamp,
+                    });
+                self.index.insert(id, (order.side, price));
+
+                if trades.is_empty() {
+                    OrderStatus::Open
+                } else {
+                    OrderStatus::PartiallyFilled
+                }
+            }
+        };
+
+        Ok(Execution {
+            order_id: id,
+            status,
+            trades,
+            remaining,
+        })
+    }
+
+    /// takes a resting order out of the book
+    pub fn cancel(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        let (side, level) = self.index.remove(&id).ok_or(OrderError::UnknownOrder(id))?;
+        let orders = self.side_mut(side);
+        let queue = orders.get_mut(&level).ok_or(OrderError::UnknownOrder(id))?;
+        let position = queue
+            .iter()
+            .position(|order| order.id == id)
+            .ok_or(OrderError::UnknownOrder(id))?;
+        let order = queue.remove(position).ok_or(OrderError::UnknownOrder(id))?;
+
+        if queue.is_empty() {
+            orders.remove(&level);
+        }
+
+        Ok(order)
+    }
+
+    /// resting order by id
+    pub fn order(&self, id: OrderId) -> Option<&Order> {
+        let (side, level) = self.index.get(&id)?;
+        let orders = match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        };
+        orders.get(level)?.iter().find(|order| order.id == id)
+    }
+
+    /// applies a corporate action to the resting orders, they keep their place in the queue,
+    /// levels that round to the same adjusted price are merged in arrival order
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        for orders in [&mut self.bids, &mut self.asks] {
+            let mut adjusted: BTreeMap<Price, VecDeque<Order>> = BTreeMap::new();
+            for (level, queue) in std::mem::take(orders) {
+                let merged = adjusted.entry(adjustment.price(level)).or_default();
+                merged.extend(queue.into_iter().map(|mut order| {
+                    order.price = adjustment.price(order.price);
+                    order.quantity = adjustment.volume(order.quantity).max(1);
+                    order.remaining = adjustment.volume(order.remaining).max(1);
+                    order
+                }));
+                merged.make_contiguous().sort_by_key(|order| order.id);
+            }
+            *orders = adjusted;
+        }
+        for (_, level) in self.index.values_mut() {
+            *level = adjustment.price(*level);
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<DepthLevel> {
+        self.bids.iter().next_back().map(Self::depth_level)
+    }
+
+    pub fn best_ask(&self) -> Option<DepthLevel> {
+        self.asks.iter().next().map(Self::depth_level)
+    }
+
+    /// aggregated quantities of the best `levels` prices on each side
+    pub fn depth(&self, levels: usize) -> Depth {
+        Depth {
+            bids: self
+                .bids
+                .iter()
+                .rev()
+                .take(levels)
+                .map(Self::depth_level)
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(Self::depth_level)
+                .collect(),
+        }
+    }
+
+    fn depth_level((level, orders): (&Price, &VecDeque<Order>)) -> DepthLevel {
+        DepthLevel {
+            price: *level,
+            quantity: orders.iter().map(|order| order.remaining).sum(),
+            orders: orders.len(),
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price, VecDeque<Order>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// highest bid or lowest ask along with its queue
+    fn best_level_mut(&mut self, side: Side) -> Option<(Price, &mut VecDeque<Order>)> {
+        let best = match side {
+            Side::Buy => self.bids.iter_mut().next_back(),
+            Side::Sell => self.asks.iter_mut().next(),
+        };
+        best.map(|(level, orders)| (*level, orders))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(text: &str) -> Price {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_limit_orders_rest_and_match_in_price_time_priority() {
+        let mut book = OrderBook::default();
+
+        book.submit(1, NewOrder::limit(Side::Sell, price("10.0"), 100), 1)
+            .unwrap();
+        book.submit(2, NewOrder::limit(Side::Sell, price("10.0"), 50), 2)
+            .unwrap();
+        book.submit(3, NewOrder::limit(Side::Sell, price("9.5"), 20), 3)
+            .unwrap();
+        let execution = book
+            .submit(4, NewOrder::limit(Side::Buy, price("9.0"), 10), 4)
+            .unwrap();
+        assert_eq!(execution.status, OrderStatus::Open);
+        assert!(execution.trades.is_empty());
+
+        assert_eq!(
+            book.depth(5),
+            Depth {
+                bids: vec![DepthLevel {
+                    price: price("9.0"),
+                    quantity: 10,
+                    orders: 1
+                }],
+                asks: vec![
+                    DepthLevel {
+                        price: price("9.5"),
+                        quantity: 20,
+                        orders: 1
+                    },
+                    DepthLevel {
+                        price: price("10.0"),
+                        quantity: 150,
+                        orders: 2
+                    },
+                ],
+            }
+        );
+
+        // best price first, then the older order at 10
+        let execution = book
+            .submit(5, NewOrder::limit(Side::Buy, price("10.0"), 70), 5)
+            .unwrap();
+        assert_eq!(execution.status, OrderStatus::Filled);
+        let fills: Vec<(OrderId, Price, u64)> = execution
+            .trades
+            .iter()
+            .map(|trade| (trade.maker_id, trade.price, trade.quantity))
+            .collect();
+        assert_eq!(fills, vec![(3, price("9.5"), 20), (1, price("10"), 50)]);
+        assert_eq!(book.order(1).unwrap().remaining, 50);
+
+        // partial fill, the rest of the buy rests at its limit
+        let execution = book
+            .submit(6, NewOrder::limit(Side::Buy, price("10.0"), 120), 6)
+            .unwrap();
+        assert_eq!(execution.status, OrderStatus::PartiallyFilled);
+        assert_eq!(execution.remaining, 20);
+        assert_eq!(book.best_bid().unwrap().price, price("10"));
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn test_market_orders_and_cancels() {
+        let mut book = OrderBook::default();
+        book.submit(1, NewOrder::limit(Side::Buy, price("10.0"), 30), 1)
+            .unwrap();
+        book.submit(2, NewOrder::limit(Side::Buy, price("9.0"), 30), 2)
+            .unwrap();
+
+        let execution = book
+            .submit(3, NewOrder::market(Side::Sell, 100), 3)
+            .unwrap();
+        assert_eq!(execution.status, OrderStatus::Cancelled);
+        assert_eq!(execution.remaining, 40);
+        assert_eq!(execution.trades.len(), 2);
+        assert_eq!(book.depth(10), Depth::default());
+
+        book.submit(4, NewOrder::limit(Side::Sell, price("11.0"), 10), 4)
+            .unwrap();
+        assert_eq!(book.cancel(4).unwrap().remaining, 10);
+        assert_eq!(book.cancel(4), Err(OrderError::UnknownOrder(4)));
+        assert_eq!(book.best_ask(), None);
+
+        assert_eq!(
+            book.submit(5, NewOrder::market(Side::Buy, 0), 5),
+            Err(OrderError::InvalidQuantity)
+        );
+        assert_eq!(
+            book.submit(6, NewOrder::limit(Side::Buy, price("-1.0"), 5), 6),
+            Err(OrderError::InvalidPrice)
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Add, Neg, Sub},
+    str::FromStr,
+};
+
+/// most decimal places a price is kept to
+pub const MAX_SCALE: u32 = 9;
+/// decimal places of a newly listed stock, cents
+pub const DEFAULT_SCALE: u32 = 2;
+
+/// Price
+/// fixed-point decimal of `units` times 10^-`scale`, 43.72 is 4372 units at scale 2,
+/// prices of different scales compare by value so 43.7 equals 43.70,
+/// serialized as a decimal string that keeps every decimal place of the scale,
+/// arithmetic saturates at the bounds of the units instead of wrapping around
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Price {
+    units: i64,
+    scale: u32,
+}
+
+impl Price {
+    pub const ZERO: Price = Price { units: 0, scale: 0 };
+
+    /// `units` of 10^-`scale`, finer scales are rounded to MAX_SCALE
+    pub fn new(units: i64, scale: u32) -> Self {
+        Self { units, scale }.round_to(scale.min(MAX_SCALE))
+    }
+
+    /// nearest price with `scale` decimal places, halves round away from zero,
+    /// values that are not finite become zero
+    pub fn from_f64(value: f64, scale: u32) -> Self {
+        let scale = scale.min(MAX_SCALE);
+        Self {
+            units: saturating_units(value * 10f64.powi(scale as i32)),
+            scale,
+        }
+    }
+
+    /// fewest decimal places that give back a float, up to a few bits of float noise
+    fn from_number(value: f64) -> Self {
+        (0..=MAX_SCALE)
+            .map(|scale| Price::from_f64(value, scale))
+            .find(|price| (price.to_f64() - value).abs() <= value.abs() * 4.0 * f64::EPSILON)
+            .unwrap_or_else(|| Price::from_f64(value, MAX_SCALE))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    pub fn units(&self) -> i64 {
+        self.units
+    }
+
+    /// decimal places
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// same value with `scale` decimal places, halves round away from zero
+    pub fn round_to(self, scale: u32) -> Self {
+        let scale = scale.min(MAX_SCALE);
+        let units = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.units,
+            Ordering::Greater => self.units.saturating_mul(10i64.pow(scale - self.scale)),
+            Ordering::Less => {
+                let divisor = 10i64.pow(self.scale - scale);
+                let (quotient, remainder) = (self.units / divisor, self.units % divisor);
+                if remainder.abs() * 2 >= divisor {
+                    quotient + self.units.signum()
+                } else {
+                    quotient
+                }
+            }
+        };
+
+        Self { units, scale }
+    }
+
+    /// same value with at least `scale` decimal places, 43.7 padded to 2 is 43.70,
+    /// finer prices are left as they are
+    pub fn padded_to(self, scale: u32) -> Self {
+        if scale > self.scale {
+            self.round_to(scale)
+        } else {
+            self
+        }
+    }
+
+    /// the price multiplied by a factor, rounded back to its own scale
+    pub fn scaled(self, factor: f64) -> Self {
+        Self {
+            units: saturating_units(self.units as f64 * factor),
+            scale: self.scale,
+        }
+    }
+
+    /// exactly halfway between two prices, one decimal place finer if need be
+    pub fn midpoint(self, other: Price) -> Self {
+        let (left, right, scale) = self.aligned(other);
+        let sum = left + right;
+
+        if sum % 2 == 0 || scale == MAX_SCALE {
+            Price::new(saturate(sum / 2), scale)
+        } else {
+            Price::new(saturate(sum * 5), scale + 1)
+        }
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.units > 0
+    }
+
+    pub fn abs(self) -> Self {
+        Self {
+            units: self.units.saturating_abs(),
+            ..self
+        }
+    }
+
+    /// units and scale with the trailing zeros dropped, equal prices normalize alike
+    fn normalized(self) -> (i64, u32) {
+        let (mut units, mut scale) = (self.units, self.scale);
+        while scale > 0 && units % 10 == 0 {
+            units /= 10;
+            scale -= 1;
+        }
+        (units, scale)
+    }
+
+    /// units of both prices at the finer of their scales
+    fn aligned(self, other: Price) -> (i128, i128, u32) {
+        let scale = self.scale.max(other.scale);
+        let widen = |price: Price| price.units as i128 * 10i128.pow(scale - price.scale);
+        (widen(self), widen(other), scale)
+    }
+}
+
+/// units clamped to the bounds of i64
+fn saturate(units: i128) -> i64 {
+    units.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// nearest units to a float, clamped to the bounds of i64, values that are not finite become zero
+fn saturating_units(units: f64) -> i64 {
+    if units.is_finite() {
+        // float to int casts saturate
+        units.round() as i64
+    } else {
+        0
+    }
+}
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (left, right, _) = self.aligned(*other);
+        left.cmp(&right)
+    }
+}
+
+impl Hash for Price {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl Add for Price {
+    type Output = Price;
+
+    fn add(self, other: Price) -> Price {
+        let (left, right, scale) = self.aligned(other);
+        Price {
+            units: saturate(left + right),
+            scale,
+        }
+    }
+}
+
+impl Sub for Price {
+    type Output = Price;
+
+    fn sub(self, other: Price) -> Price {
+        self + -other
+    }
+}
+
+impl Neg for Price {
+    type Output = Price;
+
+    fn neg(self) -> Price {
+        Price {
+            units: self.units.saturating_neg(),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Price {
+    /// every decimal place of the scale, 43.70 at scale 2
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let divisor = 10u64.pow(self.scale);
+        let (whole, fraction) = (
+            self.units.unsigned_abs() / divisor,
+            self.units.unsigned_abs() % divisor,
+        );
+        let sign = if self.units < 0 { "-" } else { "" };
+
+        if self.scale == 0 {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            let width = self.scale as usize;
+            write!(f, "{}{}.{:0width$}", sign, whole, fraction, width = width)
+        }
+    }
+}
+
+impl FromStr for Price {
+    type Err = String;
+
+    /// decimals like `43.72`, `-0.5` or `100`, with at most MAX_SCALE decimal places
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid price {:?}", s);
+        let text = s.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty())
+            || !is_digits(whole)
+            || !is_digits(fraction)
+            || fraction.len() > MAX_SCALE as usize
+        {
+            return Err(invalid());
+        }
+
+        let units: i64 = format!("{}{}", whole, fraction)
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok(Price {
+            units: if negative { -units } else { units },
+            scale: fraction.len() as u32,
+        })
+    }
+}
+
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Price {
+    /// decimal strings as well as json numbers, numbers get the fewest decimal places
+    /// that represent them, at most MAX_SCALE
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(f64),
+            Text(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(value) if value.is_finite() => Ok(Price::from_number(value)),
+            Repr::Number(value) => Err(de::Error::custom(format!("invalid price {}", value))),
+            Repr::Text(text) => text.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(text: &str) -> Price {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(price("43.72"), Price::new(4372, 2));
+        assert_eq!(price("43.70").to_string(), "43.70");
+        assert_eq!(price("-0.05").to_string(), "-0.05");
+        assert_eq!(price("100").to_string(), "100");
+        assert_eq!(price(".5"), price("0.5"));
+        assert!("4.2.1".parse::<Price>().is_err());
+        assert!("1e5".parse::<Price>().is_err());
+        assert!("0.0000000001".parse::<Price>().is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_and_rounding() {
+        assert_eq!(price("43.7"), price("43.70"));
+        assert!(price("43.7") < price("43.71"));
+        assert_eq!(price("0.1") + price("0.02"), price("0.12"));
+        assert_eq!(price("1") - price("1.25"), price("-0.25"));
+
+        assert_eq!(Price::from_f64(43.718290318, 2), price("43.72"));
+        assert_eq!(Price::from_f64(0.125, 2), price("0.13"));
+        assert_eq!(price("2.345").round_to(2), price("2.35"));
+        assert_eq!(price("-2.345").round_to(2), price("-2.35"));
+        assert_eq!(price("10.01").scaled(0.5), price("5.01"));
+        assert_eq!(price("43.7").padded_to(2).to_string(), "43.70");
+        assert_eq!(price("43.725").padded_to(2).to_string(), "43.725");
+
+        assert_eq!(price("10.00").midpoint(price("10.02")), price("10.01"));
+        assert_eq!(price("10.00").midpoint(price("10.01")), price("10.005"));
+
+        let max = Price::new(i64::MAX, 2);
+        assert_eq!((max + price("0.01")).units(), i64::MAX);
+        assert_eq!((-max - price("1")).units(), i64::MIN);
+        assert_eq!(max.scaled(2.0).units(), i64::MAX);
+        assert_eq!(max.scaled(-2.0).units(), i64::MIN);
+        assert_eq!(max.midpoint(max), max);
+    }
+
+    #[test]
+    fn test_serde_is_exact() {
+        let prices = [price("43.70"), price("0.1"), price("12345678901.12345678")];
+        let json = serde_json::to_string(&prices).unwrap();
+        assert_eq!(json, r#"["43.70","0.1","12345678901.12345678"]"#);
+
+        let read: Vec<Price> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, prices);
+        assert_eq!(read[0].scale(), 2);
+        let read: Price = serde_json::from_str("43.7").unwrap();
+        assert_eq!(read.to_string(), "43.7");
+    }
+}
@@ -0,0 +1,188 @@
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+use std::{fmt::Debug, time::Duration};
+
+/// Price Model
+/// process that decides what the next price of a single stock will be,
+/// every stock in StockData owns its own model and rounds what it produces
+/// to the decimal places of the stock
+pub trait PriceModel: Debug + Send + Sync {
+    /// produces the next price, `last_price` is None on the very first tick of the stock
+    fn next_price(&mut self, last_price: Option<f64>, rng: &mut dyn RngCore) -> f64;
+
+    /// produces the next price from a standard normal shock drawn together with the shocks
+    /// of correlated stocks, models that do not use shocks ignore it
+    fn next_price_with_shock(
+        &mut self,
+        last_price: Option<f64>,
+        _shock: f64,
+        rng: &mut dyn RngCore,
+    ) -> f64 {
+        self.next_price(last_price, rng)
+    }
+}
+
+/// every tick is an independent uniform draw in [0, scale), previous price is ignored
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UniformModel {
+    pub scale: f64,
+}
+
+impl Default for UniformModel {
+    fn default() -> Self {
+        Self { scale: 100.0 }
+    }
+}
+
+impl PriceModel for UniformModel {
+    fn next_price(&mut self, _last_price: Option<f64>, rng: &mut dyn RngCore) -> f64 {
+        rng.gen::<f64>() * self.scale
+    }
+}
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// Geometric Brownian Motion
+/// each tick multiplies the previous price by a lognormal return,
+/// drift and volatility are annualized and scaled down to the length of one tick
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GeometricBrownianMotion {
+    pub start_price: f64,
+    pub drift: f64,
+    pub volatility: f64,
+    pub tick_length: Duration,
+}
+
+impl GeometricBrownianMotion {
+    /// default parameters for the stocks we know about, anything else gets a generic profile
+    pub fn preset(stock: &str) -> Self {
+        let (start_price, drift, volatility) = match stock {
+            "GOOG" => (2800.0, 0.12, 0.28),
+            "APPL" => (150.0, 0.15, 0.30),
+            "TSLA" => (700.0, 0.20, 0.60),
+            "AMZN" => (3300.0, 0.10, 0.32),
+            "MSFT" => (300.0, 0.12, 0.25),
+            "FB" => (330.0, 0.08, 0.38),
+            _ => (100.0, 0.05, 0.25),
+        };
+
+        Self {
+            start_price,
+            drift,
+            volatility,
+            tick_length: Duration::from_secs(1),
+        }
+    }
+
+    /// default parameters for the fx rates we know about, other pairs start out at par,
+    /// rates barely drift and move far less than stocks
+    pub fn fx_preset(pair: &str) -> Self {
+        let (start_price, volatility) = match pair {
+            "EURUSD" => (1.08, 0.08),
+            "GBPUSD" => (1.27, 0.09),
+            "USDJPY" => (150.0, 0.10),
+            "USDCHF" => (0.88, 0.08),
+            "EURGBP" => (0.85, 0.06),
+            _ => (1.0, 0.10),
+        };
+
+        Self {
+            start_price,
+            drift: 0.0,
+            volatility,
+            tick_length: Duration::from_secs(1),
+        }
+    }
+
+    /// length of one tick expressed in years
+    fn dt(&self) -> f64 {
+        self.tick_length.as_secs_f64() / SECONDS_PER_YEAR
+    }
+}
+
+impl PriceModel for GeometricBrownianMotion {
+    fn next_price(&mut self, last_price: Option<f64>, rng: &mut dyn RngCore) -> f64 {
+        let shock: f64 = rng.sample(StandardNormal);
+        self.next_price_with_shock(last_price, shock, rng)
+    }
+
+    fn next_price_with_shock(
+        &mut self,
+        last_price: Option<f64>,
+        shock: f64,
+        _rng: &mut dyn RngCore,
+    ) -> f64 {
+        let last_price = last_price.unwrap_or(self.start_price);
+        let dt = self.dt();
+
+        let exponent =
+            (self.drift - self.volatility.powi(2) / 2.0) * dt + self.volatility * dt.sqrt() * shock;
+
+        last_price * exponent.exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_model() {
+        let mut thread_rng = rand::thread_rng();
+        let mut model = UniformModel { scale: 10.0 };
+
+        let mut last_price = None;
+        for _ in 0..1000 {
+            let price = model.next_price(last_price, &mut thread_rng);
+            assert!((0.0..10.0).contains(&price));
+            last_price = Some(price);
+        }
+    }
+
+    #[test]
+    fn test_geometric_brownian_motion() {
+        let mut thread_rng = rand::thread_rng();
+
+        // without volatility the price grows deterministically by the drift
+        let mut model = GeometricBrownianMotion {
+            start_price: 100.0,
+            drift: 0.1,
+            volatility: 0.0,
+            tick_length: Duration::from_secs(60 * 60 * 24),
+        };
+        let growth = (0.1 * model.dt()).exp();
+        let first = model.next_price(None, &mut thread_rng);
+        assert!((first - 100.0 * growth).abs() < 1e-9);
+        let second = model.next_price(Some(first), &mut thread_rng);
+        assert!((second - first * growth).abs() < 1e-9);
+
+        // with volatility it random-walks from the previous price and never goes negative
+        let mut model = GeometricBrownianMotion::preset("TSLA");
+        let mut last_price = None;
+        for _ in 0..1000 {
+            let price = model.next_price(last_price, &mut thread_rng);
+            assert!(price > 0.0);
+            if let Some(last_price) = last_price {
+                assert!((price / last_price - 1.0).abs() < 0.01);
+            }
+            last_price = Some(price);
+        }
+    }
+
+    #[test]
+    fn test_shocks() {
+        let mut thread_rng = rand::thread_rng();
+
+        // the same shock moves the price the same way every time
+        let mut model = GeometricBrownianMotion::preset("APPL");
+        let up = model.next_price_with_shock(Some(100.0), 2.0, &mut thread_rng);
+        let again = model.next_price_with_shock(Some(100.0), 2.0, &mut thread_rng);
+        let down = model.next_price_with_shock(Some(100.0), -2.0, &mut thread_rng);
+        assert_eq!(up, again);
+        assert!(down < 100.0 && 100.0 < up);
+
+        let mut model = UniformModel { scale: 10.0 };
+        let price = model.next_price_with_shock(None, 2.0, &mut thread_rng);
+        assert!((0.0..10.0).contains(&price));
+    }
+}
@@ -0,0 +1,208 @@
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::{spec::Spec, Adjustment, Conversion, Price, Tick, Timestamp};
+
+/// best bid and best ask of a stock along with the sizes quoted at them
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub struct Quote {
+    pub timestamp: Timestamp,
+    pub bid: Price,
+    pub bid_size: u64,
+    pub ask: Price,
+    pub ask_size: u64,
+}
+
+impl Quote {
+    pub fn spread(&self) -> Price {
+        self.ask - self.bid
+    }
+
+    pub fn mid(&self) -> Price {
+        self.bid.midpoint(self.ask)
+    }
+
+    /// same quote with bid and ask converted to another currency
+    pub fn converted(&self, conversion: Conversion) -> Self {
+        Self {
+            bid: conversion.price(self.bid),
+            ask: conversion.price(self.ask),
+            ..*self
+        }
+    }
+
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        self.bid = adjustment.price(self.bid);
+        self.ask = adjustment.price(self.ask);
+        self.bid_size = adjustment.volume(self.bid_size);
+        self.ask_size = adjustment.volume(self.ask_size);
+    }
+}
+
+/// Spread Model
+/// decides where the best bid and ask sit around the last traded price
+pub trait SpreadModel: Debug + Send + Sync {
+    /// quote right after the given tick traded
+    fn quote(&mut self, tick: Tick, rng: &mut dyn RngCore) -> Quote;
+}
+
+/// builds a spread model from a spec like `fixed:0.02:100` or `relative:5:0.5:100`,
+/// missing parameters take their defaults, none of them may be negative
+pub fn parse_spread_model(spec: &str) -> Result<Box<dyn SpreadModel>, String> {
+    let spec = Spec::parse(spec, "spread model")?;
+
+    let model: Box<dyn SpreadModel> = match spec.kind() {
+        "fixed" => Box::new(FixedSpread {
+            spread: spec.amount(0, 0.02)?,
+            size: spec.amount(1, 100.0)? as u64,
+        }),
+        "relative" => Box::new(RelativeSpread {
+            bps: spec.amount(0, 5.0)?,
+            jitter: spec.amount(1, 0.5)?,
+            median_size: spec.amount(2, 100.0)?,
+        }),
+        _ => return Err(spec.unknown()),
+    };
+
+    Ok(model)
+}
+
+/// quote centered on the last price, half the spread either side rounded to the decimal
+/// places of the price but at least one unit of them, bids never go below zero
+fn centered_quote(tick: Tick, spread: f64, bid_size: u64, ask_size: u64) -> Quote {
+    let scale = tick.price.scale();
+    let half = Price::from_f64(spread.max(0.0) / 2.0, scale).max(Price::new(1, scale));
+    Quote {
+        timestamp: tick.timestamp,
+        bid: (tick.price - half).max(Price::ZERO),
+        bid_size,
+        ask: tick.price + half,
+        ask_size,
+    }
+}
+
+/// the same absolute spread and sizes on every tick
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FixedSpread {
+    pub spread: f64,
+    pub size: u64,
+}
+
+impl Default for FixedSpread {
+    fn default() -> Self {
+        Self {
+            spread: 0.02,
+            size: 100,
+        }
+    }
+}
+
+impl SpreadModel for FixedSpread {
+    fn quote(&mut self, tick: Tick, _rng: &mut dyn RngCore) -> Quote {
+        centered_quote(tick, self.spread, self.size, self.size)
+    }
+}
+
+/// spread in basis points of the last price, widened or narrowed at random by up to `jitter`,
+/// sizes on both sides are lognormal around `median_size`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RelativeSpread {
+    pub bps: f64,
+    /// fraction the spread may move by, 0.5 keeps it between half and one and a half times `bps`
+    pub jitter: f64,
+    pub median_size: f64,
+}
+
+impl Default for RelativeSpread {
+    fn default() -> Self {
+        Self {
+            bps: 5.0,
+            jitter: 0.5,
+            median_size: 100.0,
+        }
+    }
+}
+
+impl RelativeSpread {
+    fn size(&self, rng: &mut dyn RngCore) -> u64 {
+        let shock: f64 = rng.sample(StandardNormal);
+        ((self.median_size * shock.exp()).round() as u64).max(1)
+    }
+}
+
+impl SpreadModel for RelativeSpread {
+    fn quote(&mut self, tick: Tick, rng: &mut dyn RngCore) -> Quote {
+        let jitter = self.jitter * rng.gen_range(-1.0, 1.0);
+        let spread = tick.price.to_f64() * self.bps / 10_000.0 * (1.0 + jitter);
+        let bid_size = self.size(rng);
+        let ask_size = self.size(rng);
+
+        centered_quote(tick, spread, bid_size, ask_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn tick(price: f64) -> Tick {
+        Tick {
+            seq: 1,
+            timestamp: 1_000,
+            price: Price::from_f64(price, 2),
+            volume: 1,
+        }
+    }
+
+    #[test]
+    fn test_parse_spread_model() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut fixed = parse_spread_model("fixed:0.1:50").unwrap();
+        assert_eq!(
+            fixed.quote(tick(10.0), &mut rng),
+            Quote {
+                timestamp: 1_000,
+                bid: Price::new(995, 2),
+                bid_size: 50,
+                ask: Price::new(1005, 2),
+                ask_size: 50,
+            }
+        );
+
+        assert!(parse_spread_model("relative").is_ok());
+        assert!(parse_spread_model("relative:x").is_err());
+        assert!(parse_spread_model("relative:-5").is_err());
+        assert!(parse_spread_model("fixed:inf").is_err());
+        assert!(parse_spread_model("fixed:0.02:NaN").is_err());
+        assert!(parse_spread_model("magic").is_err());
+    }
+
+    #[test]
+    fn test_relative_spread() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut model = RelativeSpread::default();
+
+        for _ in 0..100 {
+            let quote = model.quote(tick(100.0), &mut rng);
+            // 5 bps of 100 is 0.05, jittered by up to half of it and kept to whole cents
+            let spread = quote.spread();
+            assert!(spread >= Price::new(2, 2) && spread <= Price::new(8, 2));
+            assert_eq!(spread.units() % 2, 0);
+            assert_eq!(quote.mid(), Price::new(100, 0));
+            assert!(quote.bid_size >= 1 && quote.ask_size >= 1);
+        }
+
+        // 5 bps of a sub-dollar price is less than a cent, the quote still spreads a cent either side
+        let quote = model.quote(tick(0.5), &mut rng);
+        assert_eq!(
+            (quote.bid, quote.ask),
+            (Price::new(49, 2), Price::new(51, 2))
+        );
+
+        let quote = FixedSpread::default().quote(tick(0.0), &mut rng);
+        assert_eq!(quote.bid, Price::ZERO);
+    }
+}
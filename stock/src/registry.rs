@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// owned identifier of a listed instrument, e.g. "APPL"
+pub type Symbol = String;
+
+const MAX_SYMBOL_LENGTH: usize = 12;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RegistryError {
+    InvalidSymbol(String),
+    AlreadyListed(Symbol),
+    NotListed(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::InvalidSymbol(symbol) => write!(f, "invalid symbol {:?}", symbol),
+            RegistryError::AlreadyListed(symbol) => write!(f, "{} is already listed", symbol),
+            RegistryError::NotListed(symbol) => write!(f, "{} is not listed", symbol),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Symbol Registry
+/// instruments that are currently listed, kept in listing order
+/// so iterating over them is stable between runs
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SymbolRegistry {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolRegistry {
+    pub fn with_symbols<I, S>(symbols: I) -> Result<Self, RegistryError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut registry = Self::default();
+        for symbol in symbols {
+            registry.add(symbol.as_ref())?;
+        }
+        Ok(registry)
+    }
+
+    /// all listed symbols, in listing order
+    pub fn list(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// whether a symbol is listed, compared the way it was listed
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains(&normalize(symbol))
+    }
+
+    /// the symbol the way it was listed, " nflx " is NFLX
+    pub fn get(&self, symbol: &str) -> Result<Symbol, RegistryError> {
+        let symbol = normalize(symbol);
+        if self.symbols.contains(&symbol) {
+            Ok(symbol)
+        } else {
+            Err(RegistryError::NotListed(symbol))
+        }
+    }
+
+    /// lists a new symbol, symbols are upper-cased and may only hold letters, digits, '.' and '-'
+    pub fn add(&mut self, symbol: &str) -> Result<Symbol, RegistryError> {
+        let symbol = normalize(symbol);
+
+        if symbol.is_empty()
+            || symbol.len() > MAX_SYMBOL_LENGTH
+            || !symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        {
+            return Err(RegistryError::InvalidSymbol(symbol));
+        }
+
+        if self.contains(&symbol) {
+            return Err(RegistryError::AlreadyListed(symbol));
+        }
+
+        self.symbols.push(symbol.clone());
+        Ok(symbol)
+    }
+
+    /// removes a symbol from the registry, compared the way it was listed
+    pub fn delist(&mut self, symbol: &str) -> Result<Symbol, RegistryError> {
+        let symbol = normalize(symbol);
+        match self.symbols.iter().position(|s| *s == symbol) {
+            Some(index) => Ok(self.symbols.remove(index)),
+            None => Err(RegistryError::NotListed(symbol)),
+        }
+    }
+}
+
+/// symbols are listed trimmed and upper-cased
+fn normalize(symbol: &str) -> Symbol {
+    symbol.trim().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_registry() {
+        let mut registry = SymbolRegistry::with_symbols(["GOOG", "APPL"]).unwrap();
+        assert_eq!(registry.list(), &["GOOG", "APPL"]);
+
+        assert_eq!(registry.add(" nflx "), Ok("NFLX".into()));
+        assert_eq!(registry.get("nflx"), Ok("NFLX".into()));
+        assert_eq!(
+            registry.get("nope"),
+            Err(RegistryError::NotListed("NOPE".into()))
+        );
+        assert_eq!(registry.list(), &["GOOG", "APPL", "NFLX"]);
+
+        assert_eq!(
+            registry.add("GOOG"),
+            Err(RegistryError::AlreadyListed("GOOG".into()))
+        );
+        assert_eq!(
+            registry.add("BAD,ONE"),
+            Err(RegistryError::InvalidSymbol("BAD,ONE".into()))
+        );
+        assert_eq!(
+            registry.add(""),
+            Err(RegistryError::InvalidSymbol("".into()))
+        );
+
+        assert_eq!(registry.delist("GOOG"), Ok("GOOG".into()));
+        assert!(!registry.contains("GOOG"));
+        assert_eq!(
+            registry.delist("GOOG"),
+            Err(RegistryError::NotListed("GOOG".into()))
+        );
+        assert_eq!(registry.list(), &["APPL", "NFLX"]);
+
+        assert!(registry.contains(" nflx"));
+        assert_eq!(registry.delist("nflx "), Ok("NFLX".into()));
+        assert_eq!(registry.list(), &["APPL"]);
+    }
+}
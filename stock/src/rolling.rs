@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+/// Rolling Window
+/// last `capacity` prices along with their running sum, sum of squares and
+/// linearly weighted sum (oldest price weighs 1, newest weighs `len`),
+/// every aggregate is updated in O(1) per push
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RollingWindow {
+    values: VecDeque<f64>,
+    capacity: usize,
+    sum: f64,
+    sum_squares: f64,
+    weighted_sum: f64,
+    pushes: usize,
+}
+
+impl RollingWindow {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            values: VecDeque::with_capacity(capacity + 1),
+            capacity,
+            sum: 0.0,
+            sum_squares: 0.0,
+            weighted_sum: 0.0,
+            pushes: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.capacity
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn sum_squares(&self) -> f64 {
+        self.sum_squares
+    }
+
+    pub fn weighted_sum(&self) -> f64 {
+        self.weighted_sum
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(self.sum / self.values.len() as f64)
+        }
+    }
+
+    /// appends a price, evicting the oldest one once the window is full
+    pub fn push(&mut self, price: f64) {
+        if self.is_full() {
+            // every remaining price moves down one weight
+            self.weighted_sum -= self.sum;
+            if let Some(oldest) = self.values.pop_front() {
+                self.sum -= oldest;
+                self.sum_squares -= oldest * oldest;
+            }
+        }
+
+        self.values.push_back(price);
+        self.sum += price;
+        self.sum_squares += price * price;
+        self.weighted_sum += self.values.len() as f64 * price;
+
+        // running sums drift as prices come and go, resum them once per window
+        self.pushes += 1;
+        if self.pushes >= self.capacity {
+            self.pushes = 0;
+            self.recompute();
+        }
+    }
+
+    fn recompute(&mut self) {
+        self.sum = self.values.iter().sum();
+        self.sum_squares = self.values.iter().map(|price| price * price).sum();
+        self.weighted_sum = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, price)| (i + 1) as f64 * price)
+            .sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_window() {
+        let mut window = RollingWindow::new(3);
+        assert_eq!(window.mean(), None);
+
+        window.push(1.0);
+        window.push(2.0);
+        assert!(!window.is_full());
+        assert_eq!(window.sum(), 3.0);
+        assert_eq!(window.weighted_sum(), 1.0 + 4.0);
+
+        // pushes stay exact on both sides of the periodic resum
+        for price in 3..=10 {
+            window.push(price as f64);
+
+            let values: Vec<f64> = (price - 2..=price).map(|v| v as f64).collect();
+            let expected_weighted: f64 = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i + 1) as f64 * v)
+                .sum();
+
+            assert_eq!(window.len(), 3);
+            assert_eq!(window.sum(), values.iter().sum::<f64>());
+            assert_eq!(
+                window.sum_squares(),
+                values.iter().map(|v| v * v).sum::<f64>()
+            );
+            assert_eq!(window.weighted_sum(), expected_weighted);
+        }
+
+        assert_eq!(window.mean(), Some(9.0));
+    }
+}
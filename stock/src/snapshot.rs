@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{
+    Adjustment, CandleAggregator, Currency, Halt, HistorySnapshot, Price, RegistryError,
+    ScheduledAction, ScheduledJump, SessionPrices, StockSummary, Symbol, Tick, Timestamp,
+    VolumeStats,
+};
+
+/// bumped whenever the layout of a snapshot changes, older snapshots are not restored
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SnapshotError {
+    UnsupportedVersion(u32),
+    Registry(RegistryError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} is not supported, expected {}",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Registry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<RegistryError> for SnapshotError {
+    fn from(err: RegistryError) -> Self {
+        SnapshotError::Registry(err)
+    }
+}
+
+/// everything recorded for one stock, price models, quotes and resting orders are not kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StockSnapshot {
+    pub stock: Symbol,
+    /// decimal places the prices of the stock are rounded to
+    pub precision: u32,
+    pub currency: Currency,
+    pub history: HistorySnapshot,
+    pub lowest: Option<Price>,
+    pub highest: Option<Price>,
+    pub summary: Option<StockSummary>,
+    pub candles: CandleAggregator,
+    pub volume: VolumeStats,
+    pub session_prices: SessionPrices,
+    pub adjustments: Vec<Adjustment>,
+    pub halt: Option<Halt>,
+}
+
+/// Snapshot
+/// recorded state of every listed stock at a point in time, in listing order,
+/// along with the jumps and corporate actions still to come
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub taken_at: Timestamp,
+    pub stocks: Vec<StockSnapshot>,
+    pub scheduled_jumps: Vec<ScheduledJump>,
+    pub scheduled_actions: Vec<ScheduledAction>,
+}
+
+/// something recorded after a snapshot, replayed on top of it to recover what came since
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// tick at the price and volume it traded at, before any later adjustment
+    Tick {
+        stock: Symbol,
+        #[serde(flatten)]
+        tick: Tick,
+    },
+    Adjustment {
+        stock: Symbol,
+        adjustment: Adjustment,
+    },
+    /// stock listed with the decimal places of its prices, if set apart from the default
+    Listed {
+        stock: Symbol,
+        precision: Option<u32>,
+    },
+    Delisted {
+        stock: Symbol,
+    },
+    /// jump scheduled over http, it took effect once a tick of the stock at or after it follows
+    JumpScheduled {
+        jump: ScheduledJump,
+    },
+    /// corporate action scheduled over http, it took effect once its adjustment follows
+    ActionScheduled {
+        action: ScheduledAction,
+    },
+    /// everything journaled before was in the snapshot taken at that time
+    SnapshotTaken {
+        taken_at: Timestamp,
+    },
+}
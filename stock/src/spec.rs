@@ -0,0 +1,87 @@
+/// Spec
+/// `kind:param:param` setting of numeric parameters like `crossover:20:50:0.001`,
+/// parameters that are left out take their defaults
+pub(crate) struct Spec<'a> {
+    text: &'a str,
+    /// what the spec builds, for the error messages
+    what: &'static str,
+    kind: String,
+    params: Vec<f64>,
+}
+
+impl<'a> Spec<'a> {
+    pub fn parse(text: &'a str, what: &'static str) -> Result<Self, String> {
+        let mut parts = text.trim().split(':');
+        let kind = parts.next().unwrap_or_default().to_lowercase();
+        let params = parts
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("invalid {} {:?}", what, text))?;
+
+        Ok(Self {
+            text,
+            what,
+            kind,
+            params,
+        })
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn invalid(&self) -> String {
+        format!("invalid {} {:?}", self.what, self.text)
+    }
+
+    pub fn unknown(&self) -> String {
+        format!("unknown {} {:?}", self.what, self.text)
+    }
+
+    fn param(&self, index: usize, default: f64) -> f64 {
+        self.params.get(index).copied().unwrap_or(default)
+    }
+
+    /// a whole number from 1 up to `max`
+    pub fn count(&self, index: usize, default: usize, max: usize) -> Result<usize, String> {
+        let value = self.param(index, default as f64);
+        if value.fract() == 0.0 && value >= 1.0 && value <= max as f64 {
+            Ok(value as usize)
+        } else {
+            Err(self.invalid())
+        }
+    }
+
+    /// a finite number that is not negative
+    pub fn amount(&self, index: usize, default: f64) -> Result<f64, String> {
+        let value = self.param(index, default);
+        if value.is_finite() && value >= 0.0 {
+            Ok(value)
+        } else {
+            Err(self.invalid())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec() {
+        let spec = Spec::parse("Crossover: 5:20", "trend detector").unwrap();
+        assert_eq!(spec.kind(), "crossover");
+        assert_eq!(spec.count(0, 1, 100), Ok(5));
+        assert_eq!(spec.count(2, 50, 100), Ok(50));
+        assert_eq!(spec.amount(3, 0.5), Ok(0.5));
+        assert!(spec.count(1, 1, 10).is_err());
+
+        let spec = Spec::parse("fixed:-1:0.5", "spread model").unwrap();
+        assert_eq!(
+            spec.amount(0, 0.0),
+            Err("invalid spread model \"fixed:-1:0.5\"".into())
+        );
+        assert!(spec.count(1, 1, 10).is_err());
+        assert!(Spec::parse("fixed:1:x", "spread model").is_err());
+    }
+}
@@ -0,0 +1,515 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::{
+    history::RingBuffer, rolling::RollingWindow, spec::Spec, utils::get_trend, StockTrend,
+};
+
+/// trend of a stock along with what produced it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TrendReading {
+    pub direction: StockTrend,
+    /// name and parameters of the detector, e.g. "regression(300)"
+    pub detector: String,
+    /// how strongly the prices support the direction, from 0 to 1
+    pub confidence: f64,
+}
+
+/// Trend Detector
+/// decides in which direction a stock is heading from its recent prices,
+/// prices are fed one at a time and every update costs the same however many came before
+pub trait TrendDetector: Debug + Send + Sync {
+    /// name and parameters of the detector
+    fn name(&self) -> String;
+
+    /// same detector without any of the prices fed so far
+    fn fresh(&self) -> Box<dyn TrendDetector>;
+
+    /// feeds the next price
+    fn push(&mut self, price: f64);
+
+    /// direction and confidence over the prices fed so far,
+    /// NotEnoughData until the lookback is filled
+    fn current(&self) -> (StockTrend, f64);
+
+    /// direction and confidence over the given prices, ignoring the prices fed so far
+    fn detect(&self, prices: &[f64]) -> (StockTrend, f64) {
+        let mut detector = self.fresh();
+        for price in prices {
+            detector.push(*price);
+        }
+        detector.current()
+    }
+
+    fn reading(&self) -> TrendReading {
+        let (direction, confidence) = self.current();
+        TrendReading {
+            direction,
+            detector: self.name(),
+            confidence,
+        }
+    }
+}
+
+/// longest lookback a detector accepts, a day of one second ticks
+const MAX_LOOKBACK: usize = 24 * 60 * 60;
+
+/// builds a detector from a spec like `quarters:1000`, `regression:300:0.001`,
+/// `crossover:20:50:0.001` or `adx:14:25`, missing parameters take their defaults,
+/// lookbacks are whole numbers up to MAX_LOOKBACK and thresholds are not negative
+pub fn parse_trend_detector(spec: &str) -> Result<Box<dyn TrendDetector>, String> {
+    let spec = Spec::parse(spec, "trend detector")?;
+
+    let detector: Box<dyn TrendDetector> = match spec.kind() {
+        "quarters" => Box::new(QuarterComparison::new(spec.count(0, 1000, MAX_LOOKBACK)?)),
+        "regression" => Box::new(LinearRegression::new(
+            spec.count(0, 300, MAX_LOOKBACK)?,
+            spec.amount(1, 0.001)?,
+        )),
+        "crossover" => {
+            let (fast, slow) = (
+                spec.count(0, 20, MAX_LOOKBACK)?,
+                spec.count(1, 50, MAX_LOOKBACK)?,
+            );
+            if fast >= slow {
+                return Err(spec.invalid());
+            }
+            Box::new(MovingAverageCrossover::new(
+                fast,
+                slow,
+                spec.amount(2, 0.001)?,
+            ))
+        }
+        "adx" => Box::new(AverageDirectionalIndex::new(
+            spec.count(0, 14, MAX_LOOKBACK)?,
+            spec.amount(1, 25.0)?,
+        )),
+        _ => return Err(spec.unknown()),
+    };
+
+    Ok(detector)
+}
+
+/// turns a signal into a direction, signals beyond +-threshold are trends,
+/// confidence grows from 0.5 at the threshold to 1 at twice the threshold,
+/// for sideways it shrinks from 1 at no signal to 0 at the threshold
+fn classify(signal: f64, threshold: f64) -> (StockTrend, f64) {
+    if signal.abs() <= threshold {
+        let confidence = if threshold > 0.0 {
+            1.0 - signal.abs() / threshold
+        } else {
+            1.0
+        };
+        (StockTrend::Sideways, confidence)
+    } else {
+        let direction = if signal > 0.0 {
+            StockTrend::Uptrend
+        } else {
+            StockTrend::Downtrend
+        };
+        let confidence = if threshold > 0.0 {
+            (signal.abs() / threshold / 2.0).min(1.0)
+        } else {
+            1.0
+        };
+        (direction, confidence)
+    }
+}
+
+/// original detector, compares the first quarter and the second half of the lookback
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarterComparison {
+    lookback: usize,
+    prices: RingBuffer<f64>,
+}
+
+impl QuarterComparison {
+    pub fn new(lookback: usize) -> Self {
+        Self {
+            lookback,
+            prices: RingBuffer::new(lookback + 1),
+        }
+    }
+}
+
+impl Default for QuarterComparison {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl TrendDetector for QuarterComparison {
+    fn name(&self) -> String {
+        format!("quarters({})", self.lookback)
+    }
+
+    fn fresh(&self) -> Box<dyn TrendDetector> {
+        Box::new(Self::new(self.lookback))
+    }
+
+    fn push(&mut self, price: f64) {
+        self.prices.push(price);
+    }
+
+    /// agreeing quarters give full confidence, disagreeing ones (sideways) half
+    fn current(&self) -> (StockTrend, f64) {
+        match get_trend(self.prices.as_slice(), self.lookback) {
+            StockTrend::NotEnoughData => (StockTrend::NotEnoughData, 0.0),
+            StockTrend::Sideways => (StockTrend::Sideways, 0.5),
+            trend => (trend, 1.0),
+        }
+    }
+}
+
+/// least squares slope over the lookback, expressed as the fraction of the average price
+/// gained or lost over the whole lookback, confidence is scaled by the fit's r squared
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearRegression {
+    lookback: usize,
+    threshold: f64,
+    window: RollingWindow,
+}
+
+impl LinearRegression {
+    pub fn new(lookback: usize, threshold: f64) -> Self {
+        Self {
+            lookback,
+            threshold,
+            window: RollingWindow::new(lookback),
+        }
+    }
+}
+
+impl TrendDetector for LinearRegression {
+    fn name(&self) -> String {
+        format!("regression({})", self.lookback)
+    }
+
+    fn fresh(&self) -> Box<dyn TrendDetector> {
+        Box::new(Self::new(self.lookback, self.threshold))
+    }
+
+    fn push(&mut self, price: f64) {
+        self.window.push(price);
+    }
+
+    fn current(&self) -> (StockTrend, f64) {
+        if self.lookback < 2 || self.window.len() < self.lookback {
+            return (StockTrend::NotEnoughData, 0.0);
+        }
+
+        // x runs from 0 to n - 1 while the window weighs its prices from 1 to n
+        let n = self.lookback as f64;
+        let sum_y = self.window.sum();
+        let mean_y = sum_y / n;
+        let sxy = self.window.weighted_sum() - sum_y - (n - 1.0) / 2.0 * sum_y;
+        let sxx = n * (n * n - 1.0) / 12.0;
+        let syy = self.window.sum_squares() - sum_y * mean_y;
+
+        // running sums leave a little noise behind when every price is the same
+        if mean_y == 0.0 || syy <= self.window.sum_squares() * 1e-12 {
+            return (StockTrend::Sideways, 1.0);
+        }
+
+        let slope = sxy / sxx;
+        let r_squared = (sxy * sxy / (sxx * syy)).min(1.0);
+        let (direction, confidence) = classify(slope * n / mean_y, self.threshold);
+
+        match direction {
+            StockTrend::Sideways => (direction, confidence),
+            _ => (direction, confidence * r_squared),
+        }
+    }
+}
+
+/// fast simple average above the slow one is an uptrend, below is a downtrend,
+/// the gap is relative to the slow average
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovingAverageCrossover {
+    fast: usize,
+    slow: usize,
+    threshold: f64,
+    fast_window: RollingWindow,
+    slow_window: RollingWindow,
+}
+
+impl MovingAverageCrossover {
+    pub fn new(fast: usize, slow: usize, threshold: f64) -> Self {
+        Self {
+            fast,
+            slow,
+            threshold,
+            fast_window: RollingWindow::new(fast),
+            slow_window: RollingWindow::new(slow),
+        }
+    }
+}
+
+impl TrendDetector for MovingAverageCrossover {
+    fn name(&self) -> String {
+        format!("crossover({}/{})", self.fast, self.slow)
+    }
+
+    fn fresh(&self) -> Box<dyn TrendDetector> {
+        Box::new(Self::new(self.fast, self.slow, self.threshold))
+    }
+
+    fn push(&mut self, price: f64) {
+        self.fast_window.push(price);
+        self.slow_window.push(price);
+    }
+
+    fn current(&self) -> (StockTrend, f64) {
+        if self.fast == 0 || self.slow == 0 {
+            return (StockTrend::NotEnoughData, 0.0);
+        }
+
+        match (self.fast_window.mean(), self.slow_window.mean()) {
+            (Some(fast), Some(slow))
+                if self.slow_window.len() == self.slow
+                    && self.fast_window.len() == self.fast
+                    && slow != 0.0 =>
+            {
+                classify((fast - slow) / slow, self.threshold)
+            }
+            _ => (StockTrend::NotEnoughData, 0.0),
+        }
+    }
+}
+
+/// average directional index, an ADX above the threshold is a trend
+/// heading in the direction of the stronger directional indicator,
+/// ticks have no range so directional moves are taken between consecutive prices
+#[derive(Debug, Clone, PartialEq)]
+pub struct AverageDirectionalIndex {
+    period: usize,
+    threshold: f64,
+    last_price: Option<f64>,
+    moves: usize,
+    /// smoothed +DM, -DM and true range of the latest `period` moves
+    plus_dm: f64,
+    minus_dm: f64,
+    true_range: f64,
+    plus_di: f64,
+    minus_di: f64,
+    dxs: usize,
+    dx_sum: f64,
+    adx: f64,
+}
+
+impl AverageDirectionalIndex {
+    pub fn new(period: usize, threshold: f64) -> Self {
+        Self {
+            period,
+            threshold,
+            last_price: None,
+            moves: 0,
+            plus_dm: 0.0,
+            minus_dm: 0.0,
+            true_range: 0.0,
+            plus_di: 0.0,
+            minus_di: 0.0,
+            dxs: 0,
+            dx_sum: 0.0,
+            adx: 0.0,
+        }
+    }
+
+    fn directional_index(&self) -> (f64, f64, f64) {
+        if self.true_range == 0.0 {
+            (0.0, 0.0, 0.0)
+        } else {
+            let plus_di = self.plus_dm / self.true_range;
+            let minus_di = self.minus_dm / self.true_range;
+            let dx = (plus_di - minus_di).abs() / (plus_di + minus_di) * 100.0;
+            (plus_di, minus_di, dx)
+        }
+    }
+
+    /// the first `period` dxs seed the adx, later ones are Wilder smoothed into it
+    fn push_dx(&mut self, dx: f64) {
+        let p = self.period as f64;
+        self.dxs += 1;
+
+        if self.dxs <= self.period {
+            self.dx_sum += dx;
+            self.adx = self.dx_sum / p;
+        } else {
+            self.adx = (self.adx * (p - 1.0) + dx) / p;
+        }
+    }
+}
+
+impl TrendDetector for AverageDirectionalIndex {
+    fn name(&self) -> String {
+        format!("adx({})", self.period)
+    }
+
+    fn fresh(&self) -> Box<dyn TrendDetector> {
+        Box::new(Self::new(self.period, self.threshold))
+    }
+
+    fn push(&mut self, price: f64) {
+        let last_price = self.last_price.replace(price);
+        let m = match last_price {
+            Some(last_price) if self.period > 0 => price - last_price,
+            _ => return,
+        };
+        self.moves += 1;
+
+        if self.moves <= self.period {
+            // the first `period` moves are summed up before smoothing starts
+            self.plus_dm += m.max(0.0);
+            self.minus_dm += (-m).max(0.0);
+            self.true_range += m.abs();
+
+            if self.moves == self.period {
+                let (_, _, dx) = self.directional_index();
+                self.push_dx(dx);
+            }
+        } else {
+            let p = self.period as f64;
+            self.plus_dm = self.plus_dm - self.plus_dm / p + m.max(0.0);
+            self.minus_dm = self.minus_dm - self.minus_dm / p + (-m).max(0.0);
+            self.true_range = self.true_range - self.true_range / p + m.abs();
+
+            let (plus_di, minus_di, dx) = self.directional_index();
+            self.plus_di = plus_di;
+            self.minus_di = minus_di;
+            self.push_dx(dx);
+        }
+    }
+
+    fn current(&self) -> (StockTrend, f64) {
+        if self.period == 0 || self.moves < 2 * self.period {
+            return (StockTrend::NotEnoughData, 0.0);
+        }
+
+        if self.adx > self.threshold {
+            let direction = if self.plus_di >= self.minus_di {
+                StockTrend::Uptrend
+            } else {
+                StockTrend::Downtrend
+            };
+            (direction, self.adx / 100.0)
+        } else {
+            (
+                StockTrend::Sideways,
+                1.0 - self.adx / self.threshold.max(f64::EPSILON),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rising(len: usize) -> Vec<f64> {
+        (0..len).map(|v| 100.0 + v as f64).collect()
+    }
+
+    fn falling(len: usize) -> Vec<f64> {
+        rising(len).into_iter().rev().collect()
+    }
+
+    fn choppy(len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|v| if v % 2 == 0 { 100.0 } else { 101.0 })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_trend_detector() {
+        assert_eq!(
+            parse_trend_detector("quarters").unwrap().name(),
+            "quarters(1000)"
+        );
+        assert_eq!(
+            parse_trend_detector("regression:60:0.01").unwrap().name(),
+            "regression(60)"
+        );
+        assert_eq!(
+            parse_trend_detector("crossover:5:10").unwrap().name(),
+            "crossover(5/10)"
+        );
+        assert_eq!(parse_trend_detector("ADX:7").unwrap().name(), "adx(7)");
+        assert!(parse_trend_detector("magic").is_err());
+        assert!(parse_trend_detector("adx:x").is_err());
+
+        for invalid in [
+            "quarters:1e12",
+            "quarters:0",
+            "regression:-5",
+            "regression:NaN",
+            "regression:60.5",
+            "crossover:50:20",
+            "adx:14:-1",
+        ] {
+            assert!(parse_trend_detector(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_detectors() {
+        let detectors: Vec<Box<dyn TrendDetector>> = vec![
+            Box::new(QuarterComparison::new(40)),
+            Box::new(LinearRegression::new(40, 0.01)),
+            Box::new(MovingAverageCrossover::new(5, 20, 0.01)),
+            Box::new(AverageDirectionalIndex::new(7, 25.0)),
+        ];
+
+        for detector in &detectors {
+            let name = detector.name();
+            assert_eq!(
+                detector.detect(&rising(10)),
+                (StockTrend::NotEnoughData, 0.0),
+                "{}",
+                name
+            );
+
+            let mut fed = detector.fresh();
+            for price in rising(100) {
+                fed.push(price);
+            }
+            let reading = fed.reading();
+            assert_eq!(reading.direction, StockTrend::Uptrend, "{}", name);
+            assert_eq!(reading.detector, name);
+            assert!(reading.confidence > 0.5, "{}", name);
+
+            let (direction, _) = detector.detect(&falling(100));
+            assert_eq!(direction, StockTrend::Downtrend, "{}", name);
+        }
+
+        // the quarters detector reads a saw tooth as a trend, the others as sideways
+        for detector in &detectors[1..] {
+            let (direction, confidence) = detector.detect(&choppy(100));
+            assert_eq!(direction, StockTrend::Sideways, "{}", detector.name());
+            assert!((0.0..=1.0).contains(&confidence));
+        }
+    }
+
+    #[test]
+    fn test_detectors_only_see_their_lookback() {
+        // a long fall followed by a rise, fed one price at a time
+        let prices: Vec<f64> = falling(5000).into_iter().chain(rising(200)).collect();
+        let detectors: Vec<Box<dyn TrendDetector>> = vec![
+            Box::new(QuarterComparison::new(100)),
+            Box::new(LinearRegression::new(100, 0.01)),
+            Box::new(MovingAverageCrossover::new(10, 50, 0.01)),
+        ];
+
+        for detector in &detectors {
+            let mut fed = detector.fresh();
+            for price in &prices {
+                fed.push(*price);
+            }
+
+            let (direction, confidence) = fed.current();
+            let (expected, expected_confidence) = detector.detect(&prices[prices.len() - 101..]);
+            assert_eq!(direction, StockTrend::Uptrend, "{}", detector.name());
+            assert_eq!(direction, expected, "{}", detector.name());
+            assert!((confidence - expected_confidence).abs() < 1e-9);
+        }
+    }
+}
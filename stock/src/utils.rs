@@ -1,15 +1,26 @@
-use crate::{Price, StockTrend};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub(crate) fn get_trend(prices: &Vec<Price>) -> StockTrend {
+use crate::{StockTrend, Timestamp};
+
+/// current time in milliseconds since the unix epoch
+pub(crate) fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as Timestamp)
+}
+
+/// compares the first quarter and the second half of the last `lookback` price moves,
+/// both going up is an uptrend, both going down a downtrend
+pub(crate) fn get_trend(prices: &[f64], lookback: usize) -> StockTrend {
     let size = prices.len();
 
-    if size <= 1000 {
+    if lookback < 4 || size <= lookback {
         StockTrend::NotEnoughData
     } else {
-        let start_index = size - 1000 - 1;
-        let low_mid_index = start_index + 250;
-        let high_mid_index = low_mid_index + 250;
-        let end_index = high_mid_index + 500;
+        let start_index = size - lookback - 1;
+        let low_mid_index = start_index + lookback / 4;
+        let high_mid_index = start_index + lookback / 2;
+        let end_index = start_index + lookback;
 
         if prices[start_index] <= prices[low_mid_index]
             && prices[high_mid_index] <= prices[end_index]
@@ -25,7 +36,7 @@ pub(crate) fn get_trend(prices: &Vec<Price>) -> StockTrend {
     }
 }
 
-pub(crate) fn moving_average(prices: &Vec<Price>) -> f64 {
+pub(crate) fn moving_average(prices: &[f64]) -> f64 {
     if prices.is_empty() {
         0.0
     } else {
@@ -34,10 +45,66 @@ pub(crate) fn moving_average(prices: &Vec<Price>) -> f64 {
     }
 }
 
+/// simple average of the last `window` prices
+pub(crate) fn simple_moving_average(prices: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || prices.len() < window {
+        None
+    } else {
+        Some(moving_average(&prices[prices.len() - window..]))
+    }
+}
+
+/// exponential average with smoothing 2 / (window + 1),
+/// seeded with the simple average of the first `window` prices
+pub(crate) fn exponential_moving_average(prices: &[f64], window: usize) -> Option<f64> {
+    let seed = simple_moving_average(&prices[..window.min(prices.len())], window)?;
+    let alpha = 2.0 / (window as f64 + 1.0);
+
+    Some(
+        prices[window..]
+            .iter()
+            .fold(seed, |ema, price| alpha * price + (1.0 - alpha) * ema),
+    )
+}
+
+/// linearly weighted average of the last `window` prices, the latest price weighs `window`
+pub(crate) fn weighted_moving_average(prices: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || prices.len() < window {
+        None
+    } else {
+        let weighted_sum: f64 = prices[prices.len() - window..]
+            .iter()
+            .enumerate()
+            .map(|(i, price)| (i + 1) as f64 * price)
+            .sum();
+        let weights = (window * (window + 1) / 2) as f64;
+
+        Some(weighted_sum / weights)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_windowed_moving_averages() {
+        let prices = vec![1., 2., 3., 4., 5., 6.];
+
+        assert_eq!(simple_moving_average(&prices, 3), Some(5.0));
+        assert_eq!(simple_moving_average(&prices, 6), Some(3.5));
+        assert_eq!(simple_moving_average(&prices, 7), None);
+        assert_eq!(simple_moving_average(&prices, 0), None);
+
+        // seeded with (1 + 2 + 3) / 3 = 2, then alpha = 0.5 for 4, 5 and 6
+        assert_eq!(exponential_moving_average(&prices, 3), Some(5.0));
+        assert_eq!(exponential_moving_average(&prices, 7), None);
+
+        // (4 * 1 + 5 * 2 + 6 * 3) / 6
+        assert_eq!(weighted_moving_average(&prices, 3), Some(32.0 / 6.0));
+        assert_eq!(weighted_moving_average(&prices, 7), None);
+    }
+
     #[test]
     fn test_moving_average() {
         let no_prices = vec![];
@@ -50,20 +117,30 @@ mod tests {
     #[test]
     fn test_get_trend() {
         let small_data_set = vec![1., 2., 3., 4., 5., 6.];
-        assert_eq!(get_trend(&small_data_set), StockTrend::NotEnoughData);
+        assert_eq!(get_trend(&small_data_set, 1000), StockTrend::NotEnoughData);
 
-        let large_uptrend_data_set: Vec<f64> = (0..1001).into_iter().map(|v| v as f64).collect();
-        assert_eq!(get_trend(&large_uptrend_data_set), StockTrend::Uptrend);
+        let large_uptrend_data_set: Vec<f64> = (0..1001).map(|v| v as f64).collect();
+        assert_eq!(
+            get_trend(&large_uptrend_data_set, 1000),
+            StockTrend::Uptrend
+        );
 
-        let large_uptrend_data_set: Vec<f64> = (0..1124).into_iter().map(|v| v as f64).collect();
-        assert_eq!(get_trend(&large_uptrend_data_set), StockTrend::Uptrend);
+        let large_uptrend_data_set: Vec<f64> = (0..1124).map(|v| v as f64).collect();
+        assert_eq!(
+            get_trend(&large_uptrend_data_set, 1000),
+            StockTrend::Uptrend
+        );
 
-        let large_downtrend_data_set: Vec<f64> =
-            (0..1001).into_iter().map(|v| v as f64).rev().collect();
-        assert_eq!(get_trend(&large_downtrend_data_set), StockTrend::Downtrend);
+        let large_downtrend_data_set: Vec<f64> = (0..1001).map(|v| v as f64).rev().collect();
+        assert_eq!(
+            get_trend(&large_downtrend_data_set, 1000),
+            StockTrend::Downtrend
+        );
 
-        let large_downtrend_data_set: Vec<f64> =
-            (0..1451).into_iter().map(|v| v as f64).rev().collect();
-        assert_eq!(get_trend(&large_downtrend_data_set), StockTrend::Downtrend);
+        let large_downtrend_data_set: Vec<f64> = (0..1451).map(|v| v as f64).rev().collect();
+        assert_eq!(
+            get_trend(&large_downtrend_data_set, 1000),
+            StockTrend::Downtrend
+        );
     }
 }
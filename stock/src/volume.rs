@@ -0,0 +1,141 @@
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::{candles::Interval, Adjustment, Price, Tick, Timestamp};
+
+/// Volume Model
+/// decides how many shares of a single stock trade on each tick,
+/// every stock in StockData owns its own model
+pub trait VolumeModel: Debug + Send + Sync {
+    /// size traded at the given price, at least 1
+    fn next_volume(&mut self, price: Price, rng: &mut dyn RngCore) -> u64;
+}
+
+/// trade sizes are lognormal around a median, most trades are small with the odd block trade
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LogNormalVolume {
+    /// typical number of shares per tick
+    pub median: f64,
+    /// spread of the sizes, 0 trades the median every tick
+    pub sigma: f64,
+}
+
+impl Default for LogNormalVolume {
+    fn default() -> Self {
+        Self {
+            median: 100.0,
+            sigma: 1.0,
+        }
+    }
+}
+
+impl VolumeModel for LogNormalVolume {
+    fn next_volume(&mut self, _price: Price, rng: &mut dyn RngCore) -> u64 {
+        let shock: f64 = rng.sample(StandardNormal);
+        let volume = self.median * (self.sigma * shock).exp();
+
+        (volume.round() as u64).max(1)
+    }
+}
+
+/// traded volume of a stock, overall and in the current session
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VolumeStats {
+    /// shares traded since the stock was listed
+    pub total: u64,
+    /// start of the current session, sessions are utc days
+    pub session_start: Timestamp,
+    /// shares traded in the current session
+    pub session_volume: u64,
+    /// sum of price times volume in the current session
+    pub session_turnover: f64,
+}
+
+impl VolumeStats {
+    /// adds a tick, a tick from a later day starts a new session
+    pub fn push(&mut self, tick: Tick) {
+        let session_start = Interval::OneDay.bucket(tick.timestamp);
+        if session_start != self.session_start {
+            self.session_start = session_start;
+            self.session_volume = 0;
+            self.session_turnover = 0.0;
+        }
+
+        self.total += tick.volume;
+        self.session_volume += tick.volume;
+        self.session_turnover += tick.price.to_f64() * tick.volume as f64;
+    }
+
+    /// applies a corporate action to the volumes so far, the vwap moves with the prices
+    pub fn adjust(&mut self, adjustment: &Adjustment) {
+        self.total = adjustment.volume(self.total);
+        self.session_volume = adjustment.volume(self.session_volume);
+        self.session_turnover *= adjustment.price_factor * adjustment.volume_factor;
+    }
+
+    /// volume weighted average price of the current session, None until something traded
+    pub fn vwap(&self) -> Option<f64> {
+        if self.session_volume == 0 {
+            None
+        } else {
+            Some(self.session_turnover / self.session_volume as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn tick(timestamp: Timestamp, price: f64, volume: u64) -> Tick {
+        Tick {
+            seq: 0,
+            timestamp,
+            price: Price::from_f64(price, 2),
+            volume,
+        }
+    }
+
+    #[test]
+    fn test_log_normal_volume() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut fixed = LogNormalVolume {
+            median: 250.0,
+            sigma: 0.0,
+        };
+        assert_eq!(fixed.next_volume(Price::new(10, 0), &mut rng), 250);
+
+        let mut model = LogNormalVolume::default();
+        let volumes: Vec<u64> = (0..1000)
+            .map(|_| model.next_volume(Price::new(10, 0), &mut rng))
+            .collect();
+        assert!(volumes.iter().all(|volume| *volume >= 1));
+
+        let mut sorted = volumes.clone();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+        assert!((70..=140).contains(&median), "median {}", median);
+    }
+
+    #[test]
+    fn test_volume_stats() {
+        let day = Interval::OneDay.millis();
+        let mut stats = VolumeStats::default();
+        assert_eq!(stats.vwap(), None);
+
+        stats.push(tick(1_000, 10.0, 100));
+        stats.push(tick(2_000, 20.0, 300));
+        assert_eq!(stats.session_volume, 400);
+        assert_eq!(stats.vwap(), Some(17.5));
+
+        // next day starts a new session, the total keeps counting
+        stats.push(tick(day + 5_000, 30.0, 50));
+        assert_eq!(stats.session_start, day);
+        assert_eq!(stats.session_volume, 50);
+        assert_eq!(stats.total, 450);
+        assert_eq!(stats.vwap(), Some(30.0));
+    }
+}